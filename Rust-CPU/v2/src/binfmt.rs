@@ -0,0 +1,247 @@
+// binfmt.rs - Fixed-width binary keyspace files
+//
+// Layout (little endian):
+//   0  magic "CGBN"          4  version u16        6  flags u16 (bit 0: packed)
+//   8  length u32           12  record size u32   16  start index u64
+//  24  count u64            32  charset len u32   36  bits per symbol u8 + 3 reserved
+//  40  charset bytes, then `count` records of `record size` bytes without delimiters.
+//
+// Plain records hold the charset symbols, packed records hold the digit indices
+// at `bits per symbol` bits each (MSB first), padded to whole bytes.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const MAGIC: &[u8; 4] = b"CGBN";
+pub const VERSION: u16 = 1;
+const FIXED_HEADER: usize = 40;
+const FLAG_PACKED: u16 = 1;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BinHeader {
+    pub packed: bool,
+    pub length: usize,
+    pub charset: Vec<u8>,
+    /// Keyspace index of the first record.
+    pub start: u64,
+    /// Number of records in the file.
+    pub count: u64,
+}
+
+impl BinHeader {
+    pub fn new(charset: &[u8], length: usize, start: u64, count: u64, packed: bool) -> BinHeader {
+        BinHeader { packed, length, charset: charset.to_vec(), start, count }
+    }
+
+    pub fn bits_per_symbol(&self) -> usize {
+        let base = self.charset.len().max(2);
+        (usize::BITS - (base - 1).leading_zeros()) as usize
+    }
+
+    pub fn record_size(&self) -> usize {
+        if self.packed {
+            (self.length * self.bits_per_symbol()).div_ceil(8)
+        } else {
+            self.length
+        }
+    }
+
+    pub fn header_len(&self) -> usize {
+        FIXED_HEADER + self.charset.len()
+    }
+
+    /// Byte offset of the record for keyspace index `index`.
+    pub fn offset_of(&self, index: u64) -> u64 {
+        self.header_len() as u64 + (index - self.start) * self.record_size() as u64
+    }
+
+    pub fn file_len(&self) -> u64 {
+        self.header_len() as u64 + self.count * self.record_size() as u64
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut h = Vec::with_capacity(self.header_len());
+        h.extend_from_slice(MAGIC);
+        h.extend_from_slice(&VERSION.to_le_bytes());
+        h.extend_from_slice(&(if self.packed { FLAG_PACKED } else { 0 }).to_le_bytes());
+        h.extend_from_slice(&(self.length as u32).to_le_bytes());
+        h.extend_from_slice(&(self.record_size() as u32).to_le_bytes());
+        h.extend_from_slice(&self.start.to_le_bytes());
+        h.extend_from_slice(&self.count.to_le_bytes());
+        h.extend_from_slice(&(self.charset.len() as u32).to_le_bytes());
+        h.extend_from_slice(&[self.bits_per_symbol() as u8, 0, 0, 0]);
+        h.extend_from_slice(&self.charset);
+        h
+    }
+
+    pub fn read_from<R: Read>(r: &mut R) -> io::Result<BinHeader> {
+        let mut fixed = [0u8; FIXED_HEADER];
+        r.read_exact(&mut fixed)?;
+        if &fixed[0..4] != MAGIC {
+            return Err(invalid("not a combo_gen binary file"));
+        }
+        let version = u16::from_le_bytes([fixed[4], fixed[5]]);
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported binary format version {}", version)));
+        }
+        let flags = u16::from_le_bytes([fixed[6], fixed[7]]);
+        let length = u32::from_le_bytes(fixed[8..12].try_into().unwrap()) as usize;
+        let record_size = u32::from_le_bytes(fixed[12..16].try_into().unwrap()) as usize;
+        let start = u64::from_le_bytes(fixed[16..24].try_into().unwrap());
+        let count = u64::from_le_bytes(fixed[24..32].try_into().unwrap());
+        let charset_len = u32::from_le_bytes(fixed[32..36].try_into().unwrap()) as usize;
+        let mut charset = vec![0u8; charset_len];
+        r.read_exact(&mut charset)?;
+
+        let header = BinHeader { packed: flags & FLAG_PACKED != 0, length, charset, start, count };
+        if header.record_size() != record_size || header.bits_per_symbol() != fixed[36] as usize {
+            return Err(invalid("inconsistent binary header"));
+        }
+        Ok(header)
+    }
+
+    /// Appends the record for `digits` (charset indices) to `out`.
+    #[inline]
+    pub fn encode_record(&self, digits: &[u32], out: &mut Vec<u8>) {
+        if !self.packed {
+            out.extend(digits.iter().map(|&d| self.charset[d as usize]));
+            return;
+        }
+        let bits = self.bits_per_symbol();
+        let mut acc: u64 = 0;
+        let mut filled = 0;
+        for &d in digits {
+            acc = (acc << bits) | d as u64;
+            filled += bits;
+            while filled >= 8 {
+                filled -= 8;
+                out.push((acc >> filled) as u8);
+            }
+        }
+        if filled > 0 {
+            out.push((acc << (8 - filled)) as u8);
+        }
+    }
+
+    /// Inverse of `encode_record`.
+    pub fn decode_digits(&self, record: &[u8], digits: &mut [u32]) {
+        if !self.packed {
+            for (d, &b) in digits.iter_mut().zip(record) {
+                *d = self.charset.iter().position(|&c| c == b).unwrap_or(0) as u32;
+            }
+            return;
+        }
+        let bits = self.bits_per_symbol();
+        let mask = (1u64 << bits) - 1;
+        let mut acc: u64 = 0;
+        let mut filled = 0;
+        let mut bytes = record.iter();
+        for d in digits.iter_mut() {
+            while filled < bits {
+                acc = (acc << 8) | *bytes.next().unwrap_or(&0) as u64;
+                filled += 8;
+            }
+            filled -= bits;
+            *d = ((acc >> filled) & mask) as u32;
+        }
+    }
+}
+
+/// Random access to the records of a binary keyspace file.
+pub struct BinReader {
+    file: File,
+    header: BinHeader,
+    record: Vec<u8>,
+    digits: Vec<u32>,
+}
+
+impl BinReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<BinReader> {
+        let mut file = File::open(path)?;
+        let header = BinHeader::read_from(&mut file)?;
+        let record = vec![0u8; header.record_size()];
+        let digits = vec![0u32; header.length];
+        Ok(BinReader { file, header, record, digits })
+    }
+
+    pub fn header(&self) -> &BinHeader {
+        &self.header
+    }
+
+    pub fn len(&self) -> u64 {
+        self.header.count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.header.count == 0
+    }
+
+    /// Reads the `n`-th record of the file (keyspace index `start + n`) as
+    /// charset symbols into `out`.
+    pub fn read(&mut self, n: u64, out: &mut Vec<u8>) -> io::Result<()> {
+        if n >= self.header.count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "record out of range"));
+        }
+        self.file.seek(SeekFrom::Start(self.header.offset_of(self.header.start + n)))?;
+        self.file.read_exact(&mut self.record)?;
+        out.clear();
+        if self.header.packed {
+            self.header.decode_digits(&self.record, &mut self.digits);
+            out.extend(self.digits.iter().map(|&d| self.header.charset[d as usize]));
+        } else {
+            out.extend_from_slice(&self.record);
+        }
+        Ok(())
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(packed: bool) {
+        let header = BinHeader::new(b"abcdefghij", 5, 7, 100, packed);
+        for digits in [[0u32, 0, 0, 0, 0], [9, 8, 7, 6, 5], [1, 0, 9, 0, 1]] {
+            let mut record = Vec::new();
+            header.encode_record(&digits, &mut record);
+            assert_eq!(record.len(), header.record_size());
+            let mut decoded = [0u32; 5];
+            header.decode_digits(&record, &mut decoded);
+            assert_eq!(decoded, digits);
+        }
+    }
+
+    #[test]
+    fn plain_records_round_trip() {
+        round_trip(false);
+    }
+
+    #[test]
+    fn packed_records_round_trip() {
+        round_trip(true);
+        // 10 symbols need 4 bits each, so 5 of them fit in 3 bytes
+        assert_eq!(BinHeader::new(b"abcdefghij", 5, 0, 1, true).record_size(), 3);
+    }
+
+    #[test]
+    fn header_round_trip() {
+        for packed in [false, true] {
+            let header = BinHeader::new(b"0123456789abcdef!", 8, 42, 1 << 40, packed);
+            let bytes = header.to_bytes();
+            assert_eq!(bytes.len(), header.header_len());
+            assert_eq!(BinHeader::read_from(&mut bytes.as_slice()).unwrap(), header);
+        }
+    }
+
+    #[test]
+    fn header_rejects_other_files() {
+        let mut bytes = BinHeader::new(b"ab", 4, 0, 16, false).to_bytes();
+        bytes[0] = b'X';
+        assert!(BinHeader::read_from(&mut bytes.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,112 @@
+// codec.rs - Output compression codecs
+
+use std::io::{self, Write};
+
+use flate2::write::GzEncoder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Gzip,
+    Zstd,
+    Xz,
+    Lz4,
+    Bzip2,
+}
+
+impl Codec {
+    pub fn parse(name: &str) -> Result<Codec, String> {
+        match name {
+            "none" => Ok(Codec::None),
+            "gzip" | "gz" => Ok(Codec::Gzip),
+            "zstd" | "zst" => Ok(Codec::Zstd),
+            "xz" => Ok(Codec::Xz),
+            "lz4" => Ok(Codec::Lz4),
+            "bzip2" | "bz2" => Ok(Codec::Bzip2),
+            _ => Err(format!("unknown codec '{}' (none, gzip, zstd, xz, lz4, bzip2)", name)),
+        }
+    }
+
+    /// Codec implied by the output file extension, if any.
+    pub fn from_path(path: &str) -> Codec {
+        match path.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("gz") => Codec::Gzip,
+            Some("zst") => Codec::Zstd,
+            Some("xz") => Codec::Xz,
+            Some("lz4") => Codec::Lz4,
+            Some("bz2") => Codec::Bzip2,
+            _ => Codec::None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::None => "none",
+            Codec::Gzip => "gzip",
+            Codec::Zstd => "zstd",
+            Codec::Xz => "xz",
+            Codec::Lz4 => "lz4",
+            Codec::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Valid `--compress-level` range; lz4 has a single level.
+    pub fn levels(&self) -> (u32, u32) {
+        match self {
+            Codec::None | Codec::Lz4 => (0, 0),
+            Codec::Gzip | Codec::Xz => (0, 9),
+            Codec::Zstd => (1, 22),
+            Codec::Bzip2 => (1, 9),
+        }
+    }
+
+    /// Level used without `--compress-level`, tuned for throughput.
+    pub fn default_level(&self) -> u32 {
+        match self {
+            Codec::None | Codec::Lz4 => 0,
+            Codec::Gzip => 1,
+            Codec::Zstd => 3,
+            Codec::Xz => 1,
+            Codec::Bzip2 => 1,
+        }
+    }
+
+    pub fn check_level(&self, level: u32) -> Result<u32, String> {
+        let (lo, hi) = self.levels();
+        if level < lo || level > hi {
+            return Err(format!("{} level must be in {}..={}", self.name(), lo, hi));
+        }
+        Ok(level)
+    }
+
+    /// Compresses `data` as one self-contained member (gzip member, zstd/lz4/xz
+    /// frame or bzip2 stream). Concatenated blocks decode as a single stream, so
+    /// workers can compress in parallel and only serialize the final write.
+    pub fn compress_block(&self, data: &[u8], level: u32, out: &mut Vec<u8>) -> io::Result<()> {
+        match self {
+            Codec::None => out.extend_from_slice(data),
+            Codec::Gzip => {
+                let mut e = GzEncoder::new(out, flate2::Compression::new(level));
+                e.write_all(data)?;
+                e.finish()?;
+            }
+            Codec::Zstd => zstd::stream::copy_encode(data, out, level as i32)?,
+            Codec::Xz => {
+                let mut e = xz2::write::XzEncoder::new(out, level);
+                e.write_all(data)?;
+                e.finish()?;
+            }
+            Codec::Lz4 => {
+                let mut e = lz4_flex::frame::FrameEncoder::new(out);
+                e.write_all(data)?;
+                e.finish().map_err(io::Error::other)?;
+            }
+            Codec::Bzip2 => {
+                let mut e = bzip2::write::BzEncoder::new(out, bzip2::Compression::new(level));
+                e.write_all(data)?;
+                e.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
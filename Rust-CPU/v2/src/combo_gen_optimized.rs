@@ -13,6 +13,9 @@ use std::time::Instant;
 use indicatif::{ProgressBar, ProgressStyle};
 use flate2::write::GzEncoder;
 use flate2::Compression;
+use combo_gen::codec::Codec;
+use combo_gen::status;
+use combo_gen::stdio;
 
 fn default_charset() -> Vec<u8> {
     (33u8..=126u8).collect() // printable ASCII
@@ -99,7 +102,15 @@ pub fn main() {
             "--charset" => { i += 1; charset = args[i].as_bytes().to_vec(); }
             "--batch" => { i += 1; batch_size = args[i].parse().expect("batch must be integer"); }
             "--resume" => { i += 1; resume_file = Some(args[i].clone()); }
-            "--compress" => { i += 1; compress = matches!(args[i].as_str(), "gzip"); }
+            "--compress" => {
+                i += 1;
+                compress = match Codec::parse(&args[i]) {
+                    Ok(Codec::Gzip) => true,
+                    Ok(Codec::None) => false,
+                    Ok(codec) => { eprintln!("Error: {} compression is only supported by the max engine (gzip, none)", codec.name()); std::process::exit(1); }
+                    Err(e) => { eprintln!("Error: {}", e); std::process::exit(1); }
+                };
+            }
             "--memory" => { memory_only = true; }
             "--verbose" => { verbose = true; }
             "--dry-run" => { dry_run = true; }
@@ -108,6 +119,9 @@ pub fn main() {
         i += 1;
     }
 
+    // `--output -` streams to stdout, status goes to stderr
+    stdio::set_status_to_stderr(output_path == "-" && !(memory_only || dry_run));
+
     // Validate charset
     if charset.is_empty() {
         eprintln!("Error: charset cannot be empty");
@@ -126,18 +140,18 @@ pub fn main() {
     };
 
     let effective_total = limit.map_or(total, |l| l.min(total));
-    if effective_total == 0 { println!("Nothing to do."); return; }
-
-    println!("Charset size: {}", base);
-    println!("Code length: {}", length);
-    println!("Total combinations: {}", total);
-    println!("Threads: {}", threads);
-    println!("Effective total: {}", effective_total);
-    println!("Buffer size: {} KB", batch_size / 1024);
-    println!("Output path: {}", if memory_only || dry_run { "(none)" } else { &output_path });
-    if compress { println!("Compression: gzip"); }
-    if dry_run { println!("Mode: Dry-run (no output)"); }
-    if memory_only { println!("Mode: Memory-only (no file output)"); }
+    if effective_total == 0 { status!("Nothing to do."); return; }
+
+    status!("Charset size: {}", base);
+    status!("Code length: {}", length);
+    status!("Total combinations: {}", total);
+    status!("Threads: {}", threads);
+    status!("Effective total: {}", effective_total);
+    status!("Buffer size: {} KB", batch_size / 1024);
+    status!("Output path: {}", if memory_only || dry_run { "(none)" } else { &output_path });
+    if compress { status!("Compression: gzip"); }
+    if dry_run { status!("Mode: Dry-run (no output)"); }
+    if memory_only { status!("Mode: Memory-only (no file output)"); }
 
     // Resume support
     let start_index = if let Some(ref resume) = resume_file {
@@ -145,14 +159,14 @@ pub fn main() {
             let resume_str = std::fs::read_to_string(resume).unwrap_or_else(|_| "0".to_string());
             let idx = resume_str.trim().parse().unwrap_or(0);
             if idx > 0 {
-                println!("Resuming from index: {}", idx);
+                status!("Resuming from index: {}", idx);
             }
             idx
         } else { 0 }
     } else { 0 };
 
     if start_index >= effective_total {
-        println!("Resume index {} >= effective total {}. Nothing to do.", start_index, effective_total);
+        status!("Resume index {} >= effective total {}. Nothing to do.", start_index, effective_total);
         return;
     }
 
@@ -183,7 +197,11 @@ pub fn main() {
     let output_arc: Option<Arc<Mutex<Box<dyn Write + Send>>>> = if memory_only || dry_run {
         None
     } else {
-        let file = File::create(&output_path).expect("Failed to create output file");
+        let file: Box<dyn Write + Send> = if output_path == "-" {
+            Box::new(std::io::stdout())
+        } else {
+            Box::new(File::create(&output_path).expect("Failed to create output file"))
+        };
         let writer: Box<dyn Write + Send> = if compress {
             Box::new(BufWriter::with_capacity(batch_size, GzEncoder::new(file, Compression::fast())))
         } else {
@@ -265,7 +283,7 @@ pub fn main() {
                 if !dry_run_clone && output_clone.is_some() && buf.len() >= batch_size_clone {
                     if let Some(ref out) = output_clone {
                         let mut w = out.lock().unwrap();
-                        w.write_all(&buf).expect("Failed to write to output");
+                        stdio::check_write(w.write_all(&buf), "Failed to write to output");
                     }
                     buf.clear();
                 }
@@ -284,7 +302,7 @@ pub fn main() {
             if !dry_run_clone && !buf.is_empty() {
                 if let Some(ref out) = output_clone {
                     let mut w = out.lock().unwrap();
-                    w.write_all(&buf).expect("Failed to write final buffer");
+                    stdio::check_write(w.write_all(&buf), "Failed to write final buffer");
                 }
             }
 
@@ -298,7 +316,7 @@ pub fn main() {
 
             produced_clone.fetch_add(local_count, Ordering::Relaxed);
             if verbose_clone { 
-                println!("Thread {} completed: {} combinations", tid, local_count); 
+                status!("Thread {} completed: {} combinations", tid, local_count); 
             }
         }));
     }
@@ -311,7 +329,7 @@ pub fn main() {
     // Final flush and cleanup
     if let Some(out) = output_arc {
         let mut w = out.lock().unwrap();
-        w.flush().expect("Failed to flush output");
+        stdio::check_write(w.flush(), "Failed to flush output");
     }
 
     // Save resume state
@@ -320,7 +338,7 @@ pub fn main() {
         std::fs::write(resume, final_index.to_string())
             .expect("Failed to write resume file");
         if verbose {
-            println!("Resume state saved: {}", final_index);
+            status!("Resume state saved: {}", final_index);
         }
     }
 
@@ -328,24 +346,24 @@ pub fn main() {
     let elapsed = start_time.elapsed().as_secs_f64();
     let total_done = produced.load(Ordering::Relaxed);
 
-    println!("\n═══════════════════════════════════════");
-    println!("Generated: {} combinations", total_done);
+    status!("\n═══════════════════════════════════════");
+    status!("Generated: {} combinations", total_done);
     if start_index > 0 {
-        println!("Total processed: {} (resumed from {})", start_index + total_done, start_index);
+        status!("Total processed: {} (resumed from {})", start_index + total_done, start_index);
     }
-    println!("Elapsed: {:.3} s", elapsed);
-    println!("Throughput: {:.2} M combos/sec", total_done as f64 / elapsed / 1_000_000.0);
-    println!("Speed: {:.2} MB/s", (total_done * (length + 1) as u64) as f64 / elapsed / 1_048_576.0);
-    println!("═══════════════════════════════════════");
+    status!("Elapsed: {:.3} s", elapsed);
+    status!("Throughput: {:.2} M combos/sec", total_done as f64 / elapsed / 1_000_000.0);
+    status!("Speed: {:.2} MB/s", (total_done * (length + 1) as u64) as f64 / elapsed / 1_048_576.0);
+    status!("═══════════════════════════════════════");
 
     // Display memory storage info if applicable
     if let Some(storage) = memory_storage {
         let data = storage.lock().unwrap();
-        println!("\nStored in memory: {} combinations", data.len());
+        status!("\nStored in memory: {} combinations", data.len());
         if verbose && !data.is_empty() {
-            println!("First 5 samples:");
+            status!("First 5 samples:");
             for (i, combo) in data.iter().take(5).enumerate() {
-                println!("  {}: {}", i + 1, String::from_utf8_lossy(combo));
+                status!("  {}: {}", i + 1, String::from_utf8_lossy(combo));
             }
         }
     }
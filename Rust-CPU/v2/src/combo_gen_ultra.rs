@@ -2,16 +2,36 @@
 // Build: RUSTFLAGS="-C target-cpu=native -C opt-level=3" cargo build --release
 
 use std::env;
-use std::fs::File;
-use std::io::{BufWriter, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
 use std::path::Path;
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use indicatif::{ProgressBar, ProgressStyle};
-use flate2::write::GzEncoder;
-use flate2::Compression;
+use combo_gen::binfmt::BinHeader;
+use combo_gen::codec::Codec;
+use combo_gen::dates::{self, Date, DateFormat, DateSpace, DateState};
+use combo_gen::encoding::{self, Encoding};
+use combo_gen::exclude::ExcludeSet;
+use combo_gen::exec;
+use combo_gen::freq::FrequencyTable;
+use combo_gen::hash::{self, HashOutput};
+use combo_gen::keyspace::{Keyspace, KeyspaceList, Slot};
+use combo_gen::keywalk::{KeyWalk, Layout, WalkState};
+use combo_gen::markov::{MarkovOrder, Model};
+use combo_gen::parts::{self, Manifest, PartInfo};
+use combo_gen::pattern;
+use combo_gen::record::{Format, RecordLayout};
+use combo_gen::rules::RuleSet;
+use combo_gen::schedule::Scheduler;
+use combo_gen::search::{self, HashTarget, SearchState};
+use combo_gen::status;
+use combo_gen::stdio;
+use combo_gen::variants::{self, Substitutions};
+use combo_gen::wordlist;
+use combo_gen::writer::{self, BlockSender, Writer};
 
 fn default_charset() -> Vec<u8> {
     (33u8..=126u8).collect()
@@ -51,31 +71,64 @@ fn odometer_increment(digits: &mut [u32], base: u32) -> bool {
     true
 }
 
+#[inline(always)]
+fn odometer_decrement(digits: &mut [u32], base: u32) -> bool {
+    for pos in (0..digits.len()).rev() {
+        if digits[pos] > 0 {
+            digits[pos] -= 1;
+            return false;
+        }
+        digits[pos] = base - 1;
+    }
+    true
+}
+
+// Bytes written around and between the symbols of every combination
+#[derive(Clone, Default)]
+struct Framing {
+    prefix: Vec<u8>,
+    suffix: Vec<u8>,
+    separator: Vec<u8>,
+}
+
+impl Framing {
+    fn is_empty(&self) -> bool {
+        self.prefix.is_empty() && self.suffix.is_empty() && self.separator.is_empty()
+    }
+}
+
 // Unrolled generation for common lengths
 #[inline(always)]
-fn generate_combo_fast(digits: &[u32], charset: &[u8], out: &mut Vec<u8>) {
+fn generate_combo_fast(digits: &[u32], charset: &[u8], framing: &Framing, out: &mut Vec<u8>) {
+    out.extend_from_slice(&framing.prefix);
+    if !framing.separator.is_empty() {
+        for (i, &d) in digits.iter().enumerate() {
+            if i > 0 {
+                out.extend_from_slice(&framing.separator);
+            }
+            out.push(charset[d as usize]);
+        }
+        out.extend_from_slice(&framing.suffix);
+        return;
+    }
     match digits.len() {
         1 => {
             out.push(charset[digits[0] as usize]);
-            out.push(b'\n');
         }
         2 => {
             out.push(charset[digits[0] as usize]);
             out.push(charset[digits[1] as usize]);
-            out.push(b'\n');
         }
         3 => {
             out.push(charset[digits[0] as usize]);
             out.push(charset[digits[1] as usize]);
             out.push(charset[digits[2] as usize]);
-            out.push(b'\n');
         }
         4 => {
             out.push(charset[digits[0] as usize]);
             out.push(charset[digits[1] as usize]);
             out.push(charset[digits[2] as usize]);
             out.push(charset[digits[3] as usize]);
-            out.push(b'\n');
         }
         5 => {
             out.push(charset[digits[0] as usize]);
@@ -83,7 +136,6 @@ fn generate_combo_fast(digits: &[u32], charset: &[u8], out: &mut Vec<u8>) {
             out.push(charset[digits[2] as usize]);
             out.push(charset[digits[3] as usize]);
             out.push(charset[digits[4] as usize]);
-            out.push(b'\n');
         }
         6 => {
             out.push(charset[digits[0] as usize]);
@@ -92,7 +144,6 @@ fn generate_combo_fast(digits: &[u32], charset: &[u8], out: &mut Vec<u8>) {
             out.push(charset[digits[3] as usize]);
             out.push(charset[digits[4] as usize]);
             out.push(charset[digits[5] as usize]);
-            out.push(b'\n');
         }
         7 => {
             out.push(charset[digits[0] as usize]);
@@ -102,7 +153,6 @@ fn generate_combo_fast(digits: &[u32], charset: &[u8], out: &mut Vec<u8>) {
             out.push(charset[digits[4] as usize]);
             out.push(charset[digits[5] as usize]);
             out.push(charset[digits[6] as usize]);
-            out.push(b'\n');
         }
         8 => {
             out.push(charset[digits[0] as usize]);
@@ -113,51 +163,653 @@ fn generate_combo_fast(digits: &[u32], charset: &[u8], out: &mut Vec<u8>) {
             out.push(charset[digits[5] as usize]);
             out.push(charset[digits[6] as usize]);
             out.push(charset[digits[7] as usize]);
-            out.push(b'\n');
         }
         _ => {
             for &d in digits {
                 out.push(charset[d as usize]);
             }
-            out.push(b'\n');
         }
     }
+    out.extend_from_slice(&framing.suffix);
+}
+
+// Where combinations come from; every source maps the indices 0..total to candidates
+enum Source {
+    /// charset ^ length through the unrolled fast path
+    Charset,
+    /// charset ^ length in likelihood order (--order markov), digits are ranks
+    Markov(MarkovOrder),
+    /// Patterns, word lists, hybrids and word variants
+    Spaces(KeyspaceList),
+    /// Keyboard walks (--mode keywalk)
+    Walk(KeyWalk),
+    /// Calendar dates (--mode dates)
+    Dates(DateSpace),
+}
+
+// Generation mode selected with --mode
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Charset,
+    Keywalk,
+    Dates,
+}
+
+// Read-only description of what to generate, shared by all workers
+struct Generator {
+    source: Source,
+    charset: Vec<u8>,
+    base: u64,
+    length: usize,
+    framing: Framing,
+    /// The separator alone: rules rewrite the candidate before prefix and suffix are added.
+    body: Framing,
+    layout: RecordLayout,
+    plain: bool,
+    exclude: Option<ExcludeSet>,
+    /// With rules every base combination is emitted once per rule, the rule being the fastest digit.
+    rules: Option<RuleSet>,
+    /// With --reverse, enumeration position `i` is combination `total - 1 - i`.
+    reverse: bool,
+    total: u64,
+    /// Plain charset records are emitted by patching the previous one (see Cursor::emit_run).
+    incremental: bool,
+}
+
+// A worker's position in the keyspace together with its scratch buffers
+struct Cursor<'a> {
+    gen: &'a Generator,
+    index: u64,
+    digits: Vec<u32>,
+    space: usize,
+    slot_digits: Vec<u64>,
+    walk: WalkState,
+    date: DateState,
+    candidate: Vec<u8>,
+    scratch: Vec<u8>,
+    excluded: u64,
+    rule: usize,
+    word: Vec<u8>,
+    /// The current record with its delimiter, valid while `template_index == index`.
+    template: Vec<u8>,
+    template_index: u64,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(gen: &'a Generator, start: u64) -> Cursor<'a> {
+        let position = if gen.reverse { gen.total.saturating_sub(start + 1) } else { start };
+        let mut cursor = Cursor { gen, index: start, digits: Vec::new(), space: 0, slot_digits: Vec::new(), walk: WalkState::default(), date: DateState::default(), candidate: Vec::new(), scratch: Vec::new(),
+                                  excluded: 0, rule: 0, word: Vec::new(), template: Vec::new(), template_index: u64::MAX };
+        let base_index = match gen.rules {
+            Some(ref rules) => {
+                cursor.rule = (position % rules.len() as u64) as usize;
+                position / rules.len() as u64
+            }
+            None => position,
+        };
+        match gen.source {
+            Source::Charset => {
+                cursor.digits = vec![0; gen.length];
+                index_to_digits(base_index, gen.base, &mut cursor.digits);
+            }
+            Source::Markov(ref order) => {
+                cursor.digits = vec![0; gen.length];
+                order.index_to_ranks(base_index, &mut cursor.digits);
+            }
+            Source::Spaces(ref list) => {
+                let (space, offset) = list.locate(base_index);
+                let ks = &list.spaces()[space];
+                cursor.space = space;
+                cursor.slot_digits = vec![0; ks.len()];
+                ks.index_to_digits(offset, &mut cursor.slot_digits);
+            }
+            Source::Walk(ref walk) => walk.seek(base_index, &mut cursor.walk),
+            Source::Dates(ref dates) => dates.seek(base_index, &mut cursor.date),
+        }
+        if gen.rules.is_some() {
+            cursor.refresh_word();
+        }
+        cursor
+    }
+
+    // Repositions on `start` for the next chunk, keeping the exclusion count
+    fn seek(&mut self, start: u64) {
+        *self = Cursor { excluded: self.excluded, ..Cursor::new(self.gen, start) };
+    }
+
+    // The current combination without its record delimiter
+    #[inline(always)]
+    fn render(&self, out: &mut Vec<u8>) {
+        match self.gen.rules {
+            Some(ref rules) => {
+                out.extend_from_slice(&self.gen.framing.prefix);
+                let from = out.len();
+                out.extend_from_slice(&self.word);
+                rules.apply(self.rule, out, from);
+                out.extend_from_slice(&self.gen.framing.suffix);
+            }
+            None => self.render_base(&self.gen.framing, out),
+        }
+    }
+
+    // Rules rewrite a copy of the base combination, which is rendered once for all rules
+    fn refresh_word(&mut self) {
+        let mut word = std::mem::take(&mut self.word);
+        word.clear();
+        self.render_base(&self.gen.body, &mut word);
+        self.word = word;
+    }
+
+    #[inline(always)]
+    fn render_base(&self, framing: &Framing, out: &mut Vec<u8>) {
+        match self.gen.source {
+            Source::Charset => return generate_combo_fast(&self.digits, &self.gen.charset, framing, out),
+            Source::Markov(ref order) => {
+                out.extend_from_slice(&framing.prefix);
+                order.render(&self.digits, &self.gen.charset, &framing.separator, out);
+            }
+            Source::Spaces(ref list) => {
+                out.extend_from_slice(&framing.prefix);
+                list.spaces()[self.space].render(&self.slot_digits, &framing.separator, out);
+            }
+            Source::Walk(ref walk) => {
+                out.extend_from_slice(&framing.prefix);
+                walk.render(&self.walk, &framing.separator, out);
+            }
+            Source::Dates(ref dates) => {
+                out.extend_from_slice(&framing.prefix);
+                dates.render(&self.date, out);
+            }
+        }
+        out.extend_from_slice(&framing.suffix);
+    }
+
+    // Counts and reports candidates listed in --exclude
+    #[inline(always)]
+    fn is_excluded(&mut self, candidate: &[u8]) -> bool {
+        match self.gen.exclude {
+            Some(ref set) if set.contains(candidate) => {
+                self.excluded += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The current combination as a complete output record, nothing if it is excluded
+    #[inline(always)]
+    fn emit(&mut self, out: &mut Vec<u8>) {
+        if self.gen.plain {
+            let from = out.len();
+            self.render(out);
+            if self.is_excluded(&out[from..]) {
+                out.truncate(from);
+                return;
+            }
+            out.extend_from_slice(&self.gen.layout.delimiter);
+        } else {
+            let mut candidate = std::mem::take(&mut self.candidate);
+            candidate.clear();
+            self.render(&mut candidate);
+            if !self.is_excluded(&candidate) {
+                self.gen.layout.write(self.index, self.space, &candidate, &mut self.scratch, out);
+            }
+            self.candidate = candidate;
+        }
+    }
+
+    // Emits up to `max` records and moves past them, returns how many. Plain charset
+    // records only differ from the previous one in their last symbol, except when a
+    // carry changes a few more: the record is kept as a template, the last position
+    // is swept over the charset in a tight copy loop and a carry patches only the
+    // positions it changed. Everything else goes through emit() one record at a time.
+    #[inline(always)]
+    fn emit_run(&mut self, out: &mut Vec<u8>, max: u64) -> u64 {
+        if !self.gen.incremental {
+            self.emit(out);
+            self.advance();
+            return 1;
+        }
+        let gen = self.gen;
+        if self.template_index != self.index {
+            self.template.clear();
+            generate_combo_fast(&self.digits, &gen.charset, &gen.framing, &mut self.template);
+            self.template.extend_from_slice(&gen.layout.delimiter);
+        }
+        let stride = 1 + gen.framing.separator.len();
+        let at = |pos: usize| gen.framing.prefix.len() + pos * stride;
+        let base = gen.base as u32;
+        let last = self.digits.len() - 1;
+        let last_at = at(last);
+
+        let first = self.digits[last];
+        let count = (base - first).min(max.min(base as u64) as u32);
+        out.reserve(count as usize * self.template.len());
+        for d in first..first + count {
+            self.template[last_at] = gen.charset[d as usize];
+            out.extend_from_slice(&self.template);
+        }
+        self.index += count as u64;
+
+        self.digits[last] += count;
+        if self.digits[last] == base {
+            self.digits[last] = 0;
+            let mut pos = last;
+            while pos > 0 {
+                pos -= 1;
+                self.digits[pos] += 1;
+                if self.digits[pos] < base {
+                    self.template[at(pos)] = gen.charset[self.digits[pos] as usize];
+                    break;
+                }
+                self.digits[pos] = 0;
+                self.template[at(pos)] = gen.charset[0];
+            }
+        }
+        self.template[last_at] = gen.charset[self.digits[last] as usize];
+        self.template_index = self.index;
+        count as u64
+    }
+
+    #[inline(always)]
+    fn advance(&mut self) {
+        self.index += 1;
+        if self.gen.reverse {
+            return self.retreat();
+        }
+        if let Some(ref rules) = self.gen.rules {
+            self.rule += 1;
+            if self.rule < rules.len() {
+                return;
+            }
+            self.rule = 0;
+        }
+        match self.gen.source {
+            Source::Charset => { odometer_increment(&mut self.digits, self.gen.base as u32); }
+            Source::Markov(ref order) => { order.next(&mut self.digits); }
+            Source::Spaces(ref list) => {
+                // Wrapping around a space moves on to the first combination of the next one
+                if list.spaces()[self.space].increment(&mut self.slot_digits) && self.space + 1 < list.spaces().len() {
+                    self.space += 1;
+                    self.slot_digits.clear();
+                    self.slot_digits.resize(list.spaces()[self.space].len(), 0);
+                }
+            }
+            Source::Walk(ref walk) => { walk.next(&mut self.walk); }
+            Source::Dates(ref dates) => { dates.next(&mut self.date); }
+        }
+        if self.gen.rules.is_some() {
+            self.refresh_word();
+        }
+    }
+
+    // advance() for --reverse: steps to the previous combination
+    #[inline(always)]
+    fn retreat(&mut self) {
+        if let Some(ref rules) = self.gen.rules {
+            if self.rule > 0 {
+                self.rule -= 1;
+                return;
+            }
+            self.rule = rules.len() - 1;
+        }
+        match self.gen.source {
+            Source::Charset => { odometer_decrement(&mut self.digits, self.gen.base as u32); }
+            Source::Spaces(ref list) => {
+                // Wrapping around a space moves back to the last combination of the previous one
+                if list.spaces()[self.space].decrement(&mut self.slot_digits) && self.space > 0 {
+                    self.space -= 1;
+                    let ks = &list.spaces()[self.space];
+                    self.slot_digits.resize(ks.len(), 0);
+                    ks.last_digits(&mut self.slot_digits);
+                }
+            }
+            Source::Dates(ref dates) => { dates.prev(&mut self.date); }
+            Source::Markov(_) | Source::Walk(_) => unreachable!("--reverse is rejected for markov order and keyboard walks"),
+        }
+        if self.gen.rules.is_some() {
+            self.refresh_word();
+        }
+    }
+}
+
+const PROGRESS_BATCH: u64 = 50_000;
+const WRITE_THRESHOLD: usize = 1024 * 1024; // 1MB before write
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(2);
+const SIZE_SAMPLES: u64 = 256; // records sampled to size --split-size parts
+
+// Batched progress bar updates for one worker
+struct Progress {
+    pb: ProgressBar,
+    pending: u64,
+}
+
+impl Progress {
+    #[inline(always)]
+    fn tick(&mut self) {
+        self.add(1);
+    }
+
+    #[inline(always)]
+    fn add(&mut self, n: u64) {
+        self.pending += n;
+        if self.pending >= PROGRESS_BATCH {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending > 0 {
+            self.pb.inc(self.pending);
+            self.pending = 0;
+        }
+    }
+}
+
+// Split-output plan: parts are the scheduler's chunks, numbered by their index range
+struct SplitPlan {
+    per_part: u64,
+    width: usize,
+    done: Mutex<Vec<PartInfo>>,
+}
+
+// Compresses a filled buffer as one independent block, or passes it through
+fn pack_block<'b>(codec: Codec, level: u32, buf: &'b [u8], packed: &'b mut Vec<u8>) -> std::io::Result<&'b [u8]> {
+    if codec == Codec::None {
+        return Ok(buf);
+    }
+    packed.clear();
+    codec.compress_block(buf, level, packed)?;
+    Ok(&packed[..])
+}
+
+// Hands a filled buffer to the writer thread, compressed by the worker as one independent block
+fn send_block(sender: &mut BlockSender, seq: u64, last: bool, codec: Codec, level: u32, buf: &mut Vec<u8>, written: &AtomicU64) -> std::io::Result<()> {
+    written.fetch_add(buf.len() as u64, Ordering::Relaxed);
+    let block = if codec == Codec::None {
+        std::mem::replace(buf, sender.buffer())
+    } else {
+        let mut packed = sender.buffer();
+        codec.compress_block(buf, level, &mut packed)?;
+        buf.clear();
+        packed
+    };
+    sender.send(seq, last, block)
+}
+
+// Stops all workers after a failed write; a closed pipe (`| head`) is a normal end of output
+fn write_failed(e: std::io::Error, stop: &AtomicBool, failed: &AtomicBool) {
+    if !stdio::is_broken_pipe(&e) {
+        eprintln!("Error: write failed: {}", e);
+        failed.store(true, Ordering::Relaxed);
+    }
+    stop.store(true, Ordering::Relaxed);
+}
+
+// Longest record among evenly spaced samples of `0..total`. Turning --split-size into
+// records per part with the `RecordLayout::max_len` bound, which assumes every byte is
+// escaped, would make structured parts a fraction of the requested size.
+fn sample_record_len(gen: &Generator, total: u64) -> usize {
+    let samples = SIZE_SAMPLES.min(total);
+    let mut out = Vec::new();
+    let mut longest = 0;
+    for k in 0..samples {
+        let index = ((total - 1) as u128 * k as u128 / (samples - 1).max(1) as u128) as u64;
+        out.clear();
+        Cursor::new(gen, index).emit(&mut out);
+        longest = longest.max(out.len());
+    }
+    longest
+}
+
+// Writes records from the cursor position up to `end` into a file of their own
+#[allow(clippy::too_many_arguments)]
+fn write_part(cursor: &mut Cursor, end: u64, part: u64, path: &str, header: Option<&[u8]>, codec: Codec, level: u32,
+              buf: &mut Vec<u8>, packed: &mut Vec<u8>, progress: &mut Progress, written: &AtomicU64) -> std::io::Result<PartInfo> {
+    let start = cursor.index;
+    let mut file = File::create(path)?;
+    let mut crc = crc32fast::Hasher::new();
+    let mut bytes = 0u64;
+
+    buf.clear();
+    if let Some(header) = header { buf.extend_from_slice(header); }
+    while cursor.index < end {
+        let n = cursor.emit_run(buf, end - cursor.index);
+        progress.add(n);
+
+        if buf.len() >= WRITE_THRESHOLD || cursor.index == end {
+            let data = pack_block(codec, level, buf, packed)?;
+            file.write_all(data)?;
+            crc.update(data);
+            bytes += data.len() as u64;
+            written.fetch_add(buf.len() as u64, Ordering::Relaxed);
+            buf.clear();
+        }
+    }
+
+    Ok(PartInfo { part, start, end, bytes, crc32: crc.finalize(), file: parts::file_name(path) })
+}
+
+// Writes the fixed-width records of `range` at their offset in a binary file
+fn write_binary_chunk(file: &mut File, header: &BinHeader, cursor: &mut Cursor, range: std::ops::Range<u64>,
+                      buf: &mut Vec<u8>, progress: &mut Progress, written: &AtomicU64) -> std::io::Result<()> {
+    file.seek(SeekFrom::Start(header.offset_of(range.start)))?;
+    cursor.seek(range.start);
+    buf.clear();
+    for _ in range {
+        header.encode_record(&cursor.digits, buf);
+        progress.tick();
+
+        if buf.len() >= WRITE_THRESHOLD {
+            file.write_all(buf)?;
+            written.fetch_add(buf.len() as u64, Ordering::Relaxed);
+            buf.clear();
+        }
+
+        cursor.advance();
+    }
+    if !buf.is_empty() {
+        file.write_all(buf)?;
+        written.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        buf.clear();
+    }
+    Ok(())
+}
+
+// Names the file in its write error
+fn path_error(path: &str, e: std::io::Error) -> std::io::Error {
+    std::io::Error::new(e.kind(), format!("{}: {}", path, e))
 }
 
 pub fn main() {
-    let args: Vec<String> = env::args().collect();
+    run(env::args().collect());
+}
+
+/// Runs the engine with a full argument vector (program name first).
+pub fn run(args: Vec<String>) {
     if args.len() < 2 {
-        eprintln!("Usage: {} <length> [--threads N] [--limit N] [--output path] [--charset custom] [--batch N] [--resume path] [--compress gzip|none] [--memory] [--verbose] [--dry-run]", args[0]);
+        eprintln!("Usage: {} <length> [--threads N] [--limit N] [--output path] [--charset custom] [--pattern template] [--prefix str] [--suffix str] [--separator str] [--delimiter lf|crlf|nul|none|str] [--encoding raw|hex|base64|percent|c] [--charset-hex hex] [--format text|jsonl|csv|tsv|binary|packed] [--with-length] [--with-mask] [--hash md5|sha1|sha256|ntlm] [--hash-only] [--find-hash hex] [--hash-algo md5|sha1|sha256|ntlm] [--batch N] [--chunk-size N] [--resume path] [--compress none|gzip|zstd|xz|lz4|bzip2] [--compress-level N] [--split-size N[K|M|G]] [--split-lines N] [--output-per-thread dir] [--exec cmd] [--exec-per-worker] [--exec-stop-on-success] [--ordered] [--exclude wordlist] [--rules path] [--variants path] [--no-case] [--subs a=@4,...] [--leet] [--mode charset|keywalk|dates] [--layout qwerty|azerty|keypad] [--shift] [--from YYYY-MM-DD] [--to YYYY-MM-DD] [--formats DDMMYYYY,...] [--range a..=b] [--pad N] [--order lex|markov] [--order-by charset|ascii|frequency] [--freq table.txt] [--reverse] [--stats model.bin] [--list path]... [--wordlist path] [--hybrid word+mask|mask+word] [--memory] [--verbose] [--dry-run]", args[0]);
         return;
     }
 
-    let length: usize = args[1].parse().expect("length must be integer");
-    if length == 0 {
-        eprintln!("Error: length must be greater than 0");
-        return;
-    }
+    // The length may be omitted when a pattern defines the keyspace
+    let (length, mut i) = match args[1].parse::<usize>() {
+        Ok(n) => (n, 2),
+        Err(_) if args[1].starts_with("--") => (0, 1),
+        Err(_) => { eprintln!("Error: length must be integer"); return; }
+    };
 
     let mut threads = num_cpus::get();
     let mut limit: Option<u64> = None;
     let mut output_path = String::from("combos.txt");
     let mut charset = default_charset();
     let mut batch_size: usize = 2 * 1024 * 1024; // 2MB for even better throughput
+    let mut chunk_size: Option<u64> = None;
     let mut resume_file: Option<String> = None;
-    let mut compress = false;
+    let mut compress: Option<Codec> = None;
+    let mut compress_level: Option<u32> = None;
     let mut memory_only = false;
     let mut verbose = false;
     let mut dry_run = false;
+    let mut pattern_src: Option<String> = None;
+    let mut framing = Framing::default();
+    let mut layout = RecordLayout::default();
+    let mut binary: Option<bool> = None; // Some(packed)
+    let mut split_size: Option<u64> = None;
+    let mut split_lines: Option<u64> = None;
+    let mut per_thread_dir: Option<String> = None;
+    let mut exec_cmd: Option<String> = None;
+    let mut exec_per_worker = false;
+    let mut ordered = false;
+    let mut exec_stop_on_success = false;
+    let mut hashes_only = false;
+    let mut find_hash: Option<String> = None;
+    let mut hash_algo: Option<String> = None;
+    let mut exclude_path: Option<String> = None;
+    let mut wordlist_path: Option<String> = None;
+    let mut lists: Vec<String> = Vec::new();
+    let mut rules_path: Option<String> = None;
+    let mut variants_path: Option<String> = None;
+    let mut markov_order = false;
+    let mut reverse = false;
+    let mut order_by = String::from("charset");
+    let mut freq_path: Option<String> = None;
+    let mut mode = Mode::Charset;
+    let mut date_from: Option<String> = None;
+    let mut date_to: Option<String> = None;
+    let mut date_formats: Option<String> = None;
+    let mut range_src: Option<String> = None;
+    let mut range_pad: Option<usize> = None;
+    let mut walk_layout = Layout::Qwerty;
+    let mut walk_shift = false;
+    let mut stats_path: Option<String> = None;
+    let mut variant_case = true;
+    let mut subs = Substitutions::default();
+    let mut words_first: Option<bool> = None;
 
-    let mut i = 2;
     while i < args.len() {
         match args[i].as_str() {
             "--threads" => { i += 1; threads = args[i].parse().expect("threads must be integer"); }
             "--limit" => { i += 1; limit = Some(args[i].parse().expect("limit must be integer")); }
             "--output" => { i += 1; output_path = args[i].clone(); }
             "--charset" => { i += 1; charset = args[i].as_bytes().to_vec(); }
+            "--charset-hex" => {
+                i += 1;
+                charset = encoding::decode_hex(&args[i]).unwrap_or_else(|e| { eprintln!("Error: --charset-hex: {}", e); std::process::exit(1); });
+            }
+            "--pattern" => { i += 1; pattern_src = Some(args[i].clone()); }
+            "--prefix" => { i += 1; framing.prefix = args[i].as_bytes().to_vec(); }
+            "--suffix" => { i += 1; framing.suffix = args[i].as_bytes().to_vec(); }
+            "--separator" => { i += 1; framing.separator = args[i].as_bytes().to_vec(); }
+            "--delimiter" => {
+                i += 1;
+                layout.delimiter = encoding::parse_delimiter(&args[i]).unwrap_or_else(|e| { eprintln!("Error: --delimiter: {}", e); std::process::exit(1); });
+            }
+            "--encoding" => {
+                i += 1;
+                layout.encoding = Encoding::parse(&args[i]).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); });
+            }
+            "--format" => {
+                i += 1;
+                match args[i].as_str() {
+                    "binary" => binary = Some(false),
+                    "packed" => binary = Some(true),
+                    name => layout.format = Format::parse(name).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); }),
+                }
+            }
+            "--with-length" => { layout.with_length = true; }
+            "--with-mask" => { layout.with_mask = true; }
+            "--hash" => {
+                i += 1;
+                let algo = hash::by_name(&args[i]).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); });
+                layout.hash = Some(HashOutput { algo, hashes_only: false });
+            }
+            "--hash-only" => { hashes_only = true; }
+            "--find-hash" => { i += 1; find_hash = Some(args[i].clone()); }
+            "--hash-algo" => { i += 1; hash_algo = Some(args[i].clone()); }
             "--batch" => { i += 1; batch_size = args[i].parse().expect("batch must be integer"); }
+            "--chunk-size" => {
+                i += 1;
+                chunk_size = match args[i].parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => { eprintln!("Error: --chunk-size must be a positive integer"); return; }
+                };
+            }
             "--resume" => { i += 1; resume_file = Some(args[i].clone()); }
-            "--compress" => { i += 1; compress = matches!(args[i].as_str(), "gzip"); }
+            "--compress" => {
+                i += 1;
+                compress = Some(Codec::parse(&args[i]).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); }));
+            }
+            "--compress-level" => { i += 1; compress_level = Some(args[i].parse().expect("compress-level must be integer")); }
+            "--split-size" => {
+                i += 1;
+                split_size = Some(parts::parse_size(&args[i]).unwrap_or_else(|e| { eprintln!("Error: --split-size: {}", e); std::process::exit(1); }));
+            }
+            "--split-lines" => {
+                i += 1;
+                split_lines = match args[i].parse() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => { eprintln!("Error: --split-lines must be a positive integer"); std::process::exit(1); }
+                };
+            }
+            "--output-per-thread" => { i += 1; per_thread_dir = Some(args[i].trim_end_matches(['/', '\\']).to_string()); }
+            "--exec" => { i += 1; exec_cmd = Some(args[i].clone()); }
+            "--exec-per-worker" => { exec_per_worker = true; }
+            "--ordered" => { ordered = true; }
+            "--exec-stop-on-success" => { exec_stop_on_success = true; }
+            "--mode" => {
+                i += 1;
+                mode = match args[i].as_str() {
+                    "charset" => Mode::Charset,
+                    "keywalk" => Mode::Keywalk,
+                    "dates" => Mode::Dates,
+                    other => { eprintln!("Error: unknown mode '{}' (charset, keywalk, dates)", other); std::process::exit(1); }
+                };
+            }
+            "--from" => { i += 1; date_from = Some(args[i].clone()); }
+            "--to" => { i += 1; date_to = Some(args[i].clone()); }
+            "--formats" => { i += 1; date_formats = Some(args[i].clone()); }
+            "--range" => { i += 1; range_src = Some(args[i].clone()); }
+            "--pad" => {
+                i += 1;
+                range_pad = Some(args[i].parse().unwrap_or_else(|_| { eprintln!("Error: --pad must be an integer"); std::process::exit(1); }));
+            }
+            "--layout" => {
+                i += 1;
+                walk_layout = Layout::parse(&args[i]).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); });
+            }
+            "--shift" => { walk_shift = true; }
+            "--order" => {
+                i += 1;
+                markov_order = match args[i].as_str() {
+                    "lex" => false,
+                    "markov" => true,
+                    other => { eprintln!("Error: unknown order '{}' (lex, markov)", other); std::process::exit(1); }
+                };
+            }
+            "--stats" => { i += 1; stats_path = Some(args[i].clone()); }
+            "--reverse" => { reverse = true; }
+            "--order-by" => { i += 1; order_by = args[i].clone(); }
+            "--freq" => { i += 1; freq_path = Some(args[i].clone()); }
+            "--variants" => { i += 1; variants_path = Some(args[i].clone()); }
+            "--no-case" => { variant_case = false; }
+            "--subs" => {
+                i += 1;
+                if let Err(e) = subs.add(&args[i]) { eprintln!("Error: --subs: {}", e); std::process::exit(1); }
+            }
+            "--leet" => { subs.add(variants::LEET).expect("built-in substitutions are valid"); }
+            "--rules" => { i += 1; rules_path = Some(args[i].clone()); }
+            "--list" => { i += 1; lists.push(args[i].clone()); }
+            "--wordlist" => { i += 1; wordlist_path = Some(args[i].clone()); }
+            "--hybrid" => {
+                i += 1;
+                words_first = match args[i].as_str() {
+                    "word+mask" => Some(true),
+                    "mask+word" => Some(false),
+                    other => { eprintln!("Error: unknown hybrid order '{}' (word+mask, mask+word)", other); std::process::exit(1); }
+                };
+            }
+            "--exclude" => { i += 1; exclude_path = Some(args[i].clone()); }
             "--memory" => { memory_only = true; }
             "--verbose" => { verbose = true; }
             "--dry-run" => { dry_run = true; }
@@ -166,42 +818,406 @@ pub fn main() {
         i += 1;
     }
 
+    let keyspace: Option<Keyspace> = match pattern_src {
+        Some(ref src) => match pattern::parse(src) {
+            Ok(ks) => Some(ks),
+            Err(e) => { eprintln!("Error: invalid pattern: {}", e); return; }
+        },
+        None => None,
+    };
+    // Product mode: every list is one mixed-radix digit whose tokens are its lines
+    let mut list_sizes = Vec::new();
+    let keyspace = if lists.is_empty() {
+        keyspace
+    } else if keyspace.is_some() || wordlist_path.is_some() {
+        eprintln!("Error: --list cannot be combined with --pattern or --wordlist");
+        return;
+    } else {
+        let mut slots = Vec::with_capacity(lists.len());
+        for path in &lists {
+            let data = wordlist::read(path).unwrap_or_else(|e| { eprintln!("Error: --list {}: {}", path, e); std::process::exit(1); });
+            let slot = Slot::from_tokens(wordlist::lines(&data));
+            if slot.radix() == 0 {
+                eprintln!("Error: list {} is empty", path);
+                return;
+            }
+            list_sizes.push(slot.radix());
+            slots.push(slot);
+        }
+        Some(Keyspace::new(slots))
+    };
+    // Numeric range mode: a single slot of zero-padded decimal numbers
+    let keyspace = match range_src {
+        Some(ref src) => {
+            if keyspace.is_some() {
+                eprintln!("Error: --range cannot be combined with --pattern or --list");
+                return;
+            }
+            let (start, count) = dates::parse_range(src).unwrap_or_else(|e| { eprintln!("Error: {}", e); std::process::exit(1); });
+            Some(Keyspace::new(vec![Slot::Range { start, count, width: range_pad.unwrap_or(0) }]))
+        }
+        None if range_pad.is_some() => { eprintln!("Error: --pad requires --range"); return; }
+        None => keyspace,
+    };
+    match layout.hash {
+        Some(ref mut hash) => hash.hashes_only = hashes_only,
+        None if hashes_only => { eprintln!("Error: --hash-only requires --hash"); return; }
+        None => {}
+    }
+    if keyspace.is_none() && variants_path.is_none() && mode != Mode::Dates && length == 0 {
+        eprintln!("Error: length must be greater than 0");
+        return;
+    }
     if charset.is_empty() {
         eprintln!("Error: charset cannot be empty");
         return;
     }
+    // The charset order is the collation: the first symbol is digit 0 everywhere
+    match (order_by.as_str(), &freq_path) {
+        ("charset", None) => {}
+        ("ascii", None) => charset.sort_unstable(),
+        ("frequency", Some(path)) => {
+            let table = FrequencyTable::load(path).unwrap_or_else(|e| { eprintln!("Error: --freq {}: {}", path, e); std::process::exit(1); });
+            charset = table.sort_charset(&charset);
+        }
+        ("frequency", None) => { eprintln!("Error: --order-by frequency requires --freq table.txt"); return; }
+        ("charset" | "ascii", Some(_)) => { eprintln!("Error: --freq requires --order-by frequency"); return; }
+        (other, _) => { eprintln!("Error: unknown symbol order '{}' (charset, ascii, frequency)", other); return; }
+    }
+    // Hybrid mode: a word slot in front of or behind the mask, like hashcat -a 6 / -a 7
+    if words_first.is_some() && wordlist_path.is_none() {
+        eprintln!("Error: --hybrid requires --wordlist");
+        return;
+    }
+    let words_first = words_first.unwrap_or(true);
+    let mut hybrid_words = 0;
+    let keyspace = match wordlist_path {
+        Some(ref path) => {
+            let data = wordlist::read(path).unwrap_or_else(|e| { eprintln!("Error: --wordlist {}: {}", path, e); std::process::exit(1); });
+            let words = Keyspace::new(vec![Slot::from_tokens(wordlist::lines(&data))]);
+            hybrid_words = words.total().unwrap_or(0);
+            if hybrid_words == 0 {
+                eprintln!("Error: word list {} is empty", path);
+                return;
+            }
+            let mask = keyspace.unwrap_or_else(|| Keyspace::charset(&charset, length));
+            Some(if words_first { words.chain(mask) } else { mask.chain(words) })
+        }
+        None => keyspace,
+    };
+
+    // Variant mode: the case and substitution variants of each word, word after word
+    if layout.with_mask && variants_path.is_none() {
+        eprintln!("Error: --with-mask needs --variants, every other mode generates from a single mask");
+        std::process::exit(1);
+    }
+    let mut variant_words = 0;
+    let spaces = match variants_path {
+        Some(ref path) if keyspace.is_some() => {
+            eprintln!("Error: --variants {} cannot be combined with --pattern, --list or --wordlist", path);
+            return;
+        }
+        Some(ref path) => {
+            if !variant_case && subs.is_empty() {
+                eprintln!("Error: --no-case needs --subs or --leet, otherwise every word has a single variant");
+                return;
+            }
+            let data = wordlist::read(path).unwrap_or_else(|e| { eprintln!("Error: --variants {}: {}", path, e); std::process::exit(1); });
+            let words: Vec<Keyspace> = wordlist::lines(&data).map(|w| variants::expand(w, variant_case, &subs)).collect();
+            variant_words = words.len();
+            Some(words)
+        }
+        None if variant_case && subs.is_empty() => keyspace.map(|ks| vec![ks]),
+        None => { eprintln!("Error: --no-case, --subs and --leet require --variants"); return; }
+    };
+    let spaces = match spaces.map(KeyspaceList::new) {
+        Some(Some(list)) => Some(list),
+        Some(None) => { eprintln!("Total combinations overflow u64"); return; }
+        None => None,
+    };
+    // Markov order reranks the charset per position, so it needs the plain charset keyspace
+    let markov = match (markov_order, &stats_path) {
+        (false, None) => None,
+        (false, Some(_)) => { eprintln!("Error: --stats requires --order markov"); return; }
+        (true, None) => { eprintln!("Error: --order markov requires --stats model.bin (train one with: combo_gen train corpus.txt)"); return; }
+        (true, Some(_)) if spaces.is_some() => { eprintln!("Error: --order markov only applies to charset generation, not patterns, lists or words"); return; }
+        (true, Some(path)) => {
+            let model = Model::load(path).unwrap_or_else(|e| { eprintln!("Error: --stats {}: {}", path, e); std::process::exit(1); });
+            match MarkovOrder::new(&model, &charset, length) {
+                Ok(order) => Some(order),
+                Err(e) => { eprintln!("Error: {}", e); return; }
+            }
+        }
+    };
+
+    // Keyboard walks replace the charset odometer with adjacency walks of `length` keys
+    let walk = if mode == Mode::Keywalk {
+        if spaces.is_some() || markov.is_some() {
+            eprintln!("Error: --mode keywalk cannot be combined with patterns, lists, words or markov order");
+            return;
+        }
+        if walk_shift && !walk_layout.has_shift() {
+            eprintln!("Error: the {} layout has no shift variants", walk_layout.name());
+            return;
+        }
+        match KeyWalk::new(walk_layout, length, walk_shift) {
+            Ok(w) => Some(w),
+            Err(e) => { eprintln!("Error: {}", e); return; }
+        }
+    } else {
+        None
+    };
+    // Date mode: every real day between --from and --to, once per format
+    let date_space = if mode == Mode::Dates {
+        if spaces.is_some() || markov.is_some() {
+            eprintln!("Error: --mode dates cannot be combined with patterns, lists, words or markov order");
+            return;
+        }
+        let (Some(from), Some(to)) = (&date_from, &date_to) else {
+            eprintln!("Error: --mode dates requires --from YYYY-MM-DD and --to YYYY-MM-DD");
+            return;
+        };
+        let parsed = Date::parse(from).and_then(|from| Ok((from, Date::parse(to)?)));
+        let formats: Result<Vec<DateFormat>, String> = date_formats.as_deref().unwrap_or("DDMMYYYY,MMDDYYYY,YYYYMMDD").split(',').map(DateFormat::parse).collect();
+        match parsed.and_then(|(from, to)| DateSpace::new(from, to, formats?)) {
+            Ok(d) => Some(d),
+            Err(e) => { eprintln!("Error: {}", e); return; }
+        }
+    } else if date_from.is_some() || date_to.is_some() || date_formats.is_some() {
+        eprintln!("Error: --from, --to and --formats require --mode dates");
+        return;
+    } else {
+        None
+    };
+    if reverse && (markov.is_some() || walk.is_some()) {
+        eprintln!("Error: --reverse cannot be combined with --order markov or --mode keywalk");
+        return;
+    }
+    let source = match (spaces, markov, walk, date_space) {
+        (Some(list), _, _, _) => Source::Spaces(list),
+        (_, Some(order), _, _) => Source::Markov(order),
+        (_, _, Some(walk), _) => Source::Walk(walk),
+        (_, _, _, Some(dates)) => Source::Dates(dates),
+        _ => Source::Charset,
+    };
     if threads == 0 { threads = 1; }
+    // --find-hash tests candidates in the workers and writes nothing but the match
+    let target: Option<Arc<HashTarget>> = match find_hash {
+        Some(ref hex) => {
+            let name = match (hash_algo.as_deref(), hex.trim().len()) {
+                (Some(name), _) => name,
+                (None, 40) => "sha1",
+                (None, 64) => "sha256",
+                (None, _) => { eprintln!("Error: --hash-algo is required for this hash length (md5, sha1, sha256, ntlm)"); std::process::exit(2); }
+            };
+            let target = hash::by_name(name).and_then(|algo| HashTarget::new(algo, hex));
+            match target {
+                Ok(t) => Some(Arc::new(t)),
+                Err(e) => { eprintln!("Error: --find-hash: {}", e); std::process::exit(2); }
+            }
+        }
+        None if hash_algo.is_some() => { eprintln!("Error: --hash-algo requires --find-hash"); std::process::exit(2); }
+        None => None,
+    };
+    if target.is_some() && (memory_only || dry_run || binary.is_some() || split_size.is_some() || split_lines.is_some()
+        || per_thread_dir.is_some() || exec_cmd.is_some() || layout.hash.is_some()) {
+        eprintln!("Error: --find-hash writes no output and cannot be combined with output options");
+        std::process::exit(2);
+    }
+    let no_output = memory_only || dry_run || target.is_some();
+
+    // `--output -` streams to stdout, status goes to stderr; so does --exec, whose children share our stdout
+    let to_stdout = output_path == "-" && !no_output;
+    stdio::set_status_to_stderr(to_stdout || exec_cmd.is_some());
+    if (to_stdout || exec_cmd.is_some()) && (binary.is_some() || split_size.is_some() || split_lines.is_some() || per_thread_dir.is_some()) {
+        eprintln!("Error: binary, split and per-thread output need a file path, not a stream");
+        return;
+    }
+    if exec_cmd.is_some() && (memory_only || dry_run) {
+        eprintln!("Error: --exec cannot be combined with --memory or --dry-run");
+        return;
+    }
+    if ordered && exec_per_worker {
+        eprintln!("Error: --ordered cannot be combined with --exec-per-worker, every consumer sees different chunks");
+        return;
+    }
+    if exec_cmd.is_none() && (exec_per_worker || exec_stop_on_success) {
+        eprintln!("Error: --exec-per-worker and --exec-stop-on-success require --exec");
+        return;
+    }
+    // Without --compress the codec follows the output extension (combos.txt.zst, ...)
+    let codec = compress.unwrap_or_else(|| Codec::from_path(&output_path));
+    let level = match compress_level.map(|l| codec.check_level(l)) {
+        Some(Err(e)) => { eprintln!("Error: {}", e); return; }
+        Some(Ok(l)) => l,
+        None => codec.default_level(),
+    };
+    // Binary records are fixed-width symbol strings written in place, one offset per index
+    if binary.is_some() && (!matches!(source, Source::Charset) || !framing.is_empty() || !layout.is_plain() || codec != Codec::None || memory_only || exclude_path.is_some() || rules_path.is_some() || reverse) {
+        eprintln!("Error: binary formats only support plain lexicographic charset output without pattern, framing, encoding, hashing, rules, exclusion or compression");
+        return;
+    }
+
+    let rules = match rules_path {
+        Some(ref path) => match RuleSet::load(path) {
+            Ok(r) if r.is_empty() => { eprintln!("Error: --rules {} contains no rules", path); std::process::exit(1); }
+            Ok(r) => Some(r),
+            Err(e) => { eprintln!("Error: --rules {}", e); std::process::exit(1); }
+        },
+        None => None,
+    };
 
     let base = charset.len() as u64;
-    let total = match pow_u64(base, length) {
+    let total = match source {
+        Source::Spaces(ref list) => Some(list.total()),
+        Source::Walk(ref walk) => walk.total(),
+        Source::Dates(ref dates) => Some(dates.total()),
+        Source::Charset | Source::Markov(_) => pow_u64(base, length),
+    };
+    let total = total.and_then(|t| t.checked_mul(rules.as_ref().map_or(1, |r| r.len() as u64)));
+    let total = match total {
         Some(v) => v,
         None => { eprintln!("Total combinations overflow u64"); return; }
     };
+    // Patterns render variable-width records, size estimates use the widest one
+    let (symbols, symbols_len) = match source {
+        Source::Spaces(ref list) => (list.max_slots(), list.max_len()),
+        Source::Walk(ref walk) => (length, walk.max_len()),
+        Source::Dates(ref dates) => (1, dates.max_len()),
+        Source::Charset | Source::Markov(_) => (length, length),
+    };
+    let body_len = symbols_len + framing.separator.len() * symbols.saturating_sub(1);
+    let body_len = rules.as_ref().map_or(body_len, |r| r.max_len(body_len));
+    let candidate_len = framing.prefix.len() + body_len + framing.suffix.len();
+    let record_len = layout.max_len(candidate_len);
+
+    let split = split_lines.is_some() || split_size.is_some();
+    if split && (binary.is_some() || memory_only) {
+        eprintln!("Error: split output cannot be combined with binary formats or memory mode");
+        std::process::exit(1);
+    }
+    if chunk_size.is_some() && (split || per_thread_dir.is_some()) {
+        eprintln!("Error: --chunk-size cannot be combined with split or per-thread output, their files are the chunks");
+        std::process::exit(1);
+    }
+    if per_thread_dir.is_some() && (split || binary.is_some() || memory_only) {
+        eprintln!("Error: --output-per-thread cannot be combined with split output, binary formats or memory mode");
+        std::process::exit(1);
+    }
+    let per_thread_dir = per_thread_dir.filter(|_| !dry_run);
 
     let effective_total = limit.map_or(total, |l| l.min(total));
-    if effective_total == 0 { println!("Nothing to do."); return; }
-
-    println!("╔═══════════════════════════════════════╗");
-    println!("║      ComboGen Ultra-Fast Mode         ║");
-    println!("╚═══════════════════════════════════════╝");
-    println!("Charset size: {}", base);
-    println!("Code length: {}", length);
-    println!("Total combinations: {}", total);
-    println!("Threads: {}", threads);
-    println!("Effective total: {}", effective_total);
-    println!("Buffer size: {} MB", batch_size / 1024 / 1024);
-    println!("Output: {}", if memory_only || dry_run { "(none)" } else { &output_path });
+    if effective_total == 0 { status!("Nothing to do."); return; }
+
+    status!("╔═══════════════════════════════════════╗");
+    status!("║      ComboGen Ultra-Fast Mode         ║");
+    status!("╚═══════════════════════════════════════╝");
+    if !lists.is_empty() {
+        status!("Product: {}", lists.iter().zip(&list_sizes).map(|(p, n)| format!("{} ({})", p, n)).collect::<Vec<_>>().join(" × "));
+    } else if let Some(ref src) = range_src {
+        status!("Range: {}{}", src, range_pad.map_or(String::new(), |w| format!(" (padded to {} digits)", w)));
+    } else if let Some(ref src) = pattern_src {
+        status!("Pattern: {}", src);
+        status!("Slots: {}", symbols);
+    } else if let Some(ref path) = variants_path {
+        status!("Variants: {} ({} words, {}{})", path, format_number(variant_words as u64),
+            if variant_case { "case" } else { "" }, if subs.is_empty() { "" } else if variant_case { " + substitutions" } else { "substitutions" });
+    } else if let Source::Dates(ref dates) = source {
+        let (from, to) = (dates.from(), dates.to());
+        status!("Dates: {:04}-{:02}-{:02} to {:04}-{:02}-{:02} ({} days)", from.year, from.month, from.day, to.year, to.month, to.day, format_number(dates.days()));
+        status!("Formats: {}", dates.formats().iter().map(DateFormat::as_str).collect::<Vec<_>>().join(", "));
+    } else if let Source::Walk(_) = source {
+        status!("Keyboard walks: {} keys on {}{}", length, walk_layout.name(), if walk_shift { " with shift variants" } else { "" });
+    } else {
+        status!("Charset size: {}", base);
+        status!("Code length: {}", length);
+        if let Some(ref path) = stats_path { status!("Order: markov ({})", path); }
+        if order_by != "charset" { status!("Symbol order: {} ({})", order_by, String::from_utf8_lossy(&charset)); }
+    }
+    if let Some(ref path) = wordlist_path {
+        let words = format!("{} ({} words)", path, format_number(hybrid_words));
+        if words_first { status!("Hybrid: {} + mask", words); } else { status!("Hybrid: mask + {}", words); }
+    }
+    if let (Some(path), Some(r)) = (&rules_path, &rules) {
+        status!("Rules: {} ({} rules per candidate)", path, r.len());
+    }
+    status!("Total combinations: {}", total);
+    status!("Threads: {}", threads);
+    status!("Effective total: {}", effective_total);
+    if !framing.is_empty() {
+        status!("Framing: prefix {:?}, separator {:?}, suffix {:?}",
+            String::from_utf8_lossy(&framing.prefix),
+            String::from_utf8_lossy(&framing.separator),
+            String::from_utf8_lossy(&framing.suffix));
+    }
+    if !layout.is_plain() || layout.delimiter != b"\n" {
+        status!("Record: {} format, {} encoding, delimiter {:?}", layout.format.name(), layout.encoding.name(), String::from_utf8_lossy(&layout.delimiter));
+    }
+    if let Some(ref hash) = layout.hash {
+        status!("Hash: {}{}", hash.algo.name(), if hash.hashes_only { " (hashes only)" } else { "" });
+    }
+    status!("Buffer size: {} MB", batch_size / 1024 / 1024);
+    match exec_cmd {
+        Some(ref cmd) => status!("Output: | {}{}", cmd, if exec_per_worker { " (one per worker)" } else { "" }),
+        None => status!("Output: {}", if no_output { "(none)" } else { &output_path }),
+    }
+    if codec != Codec::None && !no_output { status!("Compression: {} (level {})", codec.name(), level); }
+    if let Some(packed) = binary { status!("Format: {}", if packed { "packed binary" } else { "binary" }); }
+    let exclude = exclude_path.as_ref().map(|path| {
+        let set = ExcludeSet::load(path).unwrap_or_else(|e| { eprintln!("Error: --exclude {}: {}", path, e); std::process::exit(1); });
+        status!("Exclude: {} ({} known candidates)", path, format_number(set.len() as u64));
+        set
+    });
+    if let Some(ref t) = target { status!("Searching for {} hash {}", t.algo().name(), find_hash.as_deref().unwrap_or("").trim()); }
+
+    let generator = Arc::new(Generator {
+        plain: layout.is_plain(),
+        incremental: matches!(source, Source::Charset) && layout.is_plain() && exclude.is_none() && rules.is_none() && !reverse,
+        source,
+        charset,
+        base,
+        length,
+        body: Framing { separator: framing.separator.clone(), ..Framing::default() },
+        framing,
+        layout,
+        exclude,
+        rules,
+        reverse,
+        total,
+    });
+
+    // Split size is measured on uncompressed records, so a part only exceeds it when its
+    // records are longer than every sampled one
+    let per_part = match (split_lines, split_size) {
+        (Some(n), _) => Some(n),
+        (None, Some(bytes)) => {
+            let sampled = sample_record_len(&generator, effective_total);
+            if bytes < sampled as u64 {
+                eprintln!("Error: --split-size is smaller than one record ({} bytes)", sampled);
+                std::process::exit(1);
+            }
+            Some(bytes / sampled.max(1) as u64)
+        }
+        (None, None) => None,
+    };
+    let per_part = per_part.filter(|_| !dry_run);
 
     let start_index = if let Some(ref resume) = resume_file {
         if Path::new(resume).exists() {
             let idx = std::fs::read_to_string(resume).unwrap_or_else(|_| "0".to_string()).trim().parse().unwrap_or(0);
-            if idx > 0 { println!("Resuming from: {}", idx); }
+            if idx > 0 { status!("Resuming from: {}", idx); }
             idx
         } else { 0 }
     } else { 0 };
 
+    // Split runs restart at the beginning of the part that was interrupted
+    let start_index = match per_part {
+        Some(n) => start_index / n * n,
+        None => start_index,
+    };
+
     if start_index >= effective_total {
-        println!("Nothing to do (resume >= total)");
+        status!("Nothing to do (resume >= total)");
         return;
     }
 
@@ -215,27 +1231,99 @@ pub fn main() {
     );
 
     let produced = Arc::new(AtomicU64::new(0));
-    let resume_counter = Arc::new(AtomicU64::new(start_index));
+    let stop = Arc::new(AtomicBool::new(false));
+    let failed = Arc::new(AtomicBool::new(false));
+    let written = Arc::new(AtomicU64::new(0));
+    let excluded = Arc::new(AtomicU64::new(0));
     let start_time = Instant::now();
 
-    let mut per_thread = remaining / threads as u64;
-    let mut remainder = remaining % threads as u64;
-    if per_thread == 0 {
-        threads = remaining as usize;
-        per_thread = 1;
-        remainder = 0;
+    // Workers take fixed-size chunks from one counter instead of a 1/threads share each.
+    // Split parts and per-thread files are chunks themselves, so every file is one contiguous range
+    let chunk = match (per_part, &per_thread_dir) {
+        (Some(n), _) => n,
+        (None, Some(_)) => remaining.div_ceil(threads as u64),
+        (None, None) => chunk_size.unwrap_or_else(|| Scheduler::auto_chunk(remaining, threads)),
+    };
+    let scheduler = Arc::new(Scheduler::new(start_index..effective_total, chunk));
+    threads = threads.min(scheduler.chunks() as usize);
+    if verbose { status!("Chunks: {} of {} combinations", format_number(scheduler.chunks()), format_number(chunk)); }
+
+    let bin_header: Option<Arc<BinHeader>> = match binary {
+        Some(packed) if !dry_run => {
+            let header = BinHeader::new(&generator.charset, length, start_index, remaining, packed);
+            let created = File::create(&output_path).and_then(|mut file| {
+                file.write_all(&header.to_bytes())?;
+                file.set_len(header.file_len())
+            });
+            if let Err(e) = created {
+                write_failed(path_error(&output_path, e), &stop, &failed);
+            }
+            Some(Arc::new(header))
+        }
+        _ => None,
+    };
+
+    let split_plan: Option<Arc<SplitPlan>> = per_part.map(|n| {
+        let last_part = (effective_total - 1) / n;
+        status!("Split: {} records per part, parts {}..={}", n, start_index / n, last_part);
+        Arc::new(SplitPlan { per_part: n, width: parts::part_width(last_part + 1), done: Mutex::new(Vec::new()) })
+    });
+
+    // A resumed run keeps the files below the checkpoint and numbers its own after them,
+    // files of chunks the checkpoint does not cover are regenerated
+    let mut per_thread_manifest = Manifest { range: Some((0, effective_total)), ..Manifest::default() };
+    if let Some(ref dir) = per_thread_dir {
+        std::fs::create_dir_all(dir).expect("Failed to create output directory");
+        if start_index > 0 {
+            let path = parts::manifest_path(&format!("{}/{}", dir, parts::file_name(&output_path)));
+            per_thread_manifest.parts = Manifest::read(&path).map(|m| m.parts).unwrap_or_default();
+            per_thread_manifest.parts.retain(|p| p.end <= start_index);
+        }
+        status!("Per-thread output: {}/ ({} files)", dir, threads);
     }
+    let first_per_thread = per_thread_manifest.parts.iter().map(|p| p.part + 1).max().unwrap_or(0);
+    let width_per_thread = parts::part_width(first_per_thread + scheduler.chunks());
+    let per_thread_parts: Arc<Mutex<Vec<PartInfo>>> = Arc::new(Mutex::new(Vec::new()));
 
-    let output_arc: Option<Arc<Mutex<Box<dyn Write + Send>>>> = if memory_only || dry_run {
+    // Every output gets a writer thread; workers compress their own blocks, so it only sees finished members
+    let open_writer = |sink: Box<dyn Write + Send>| -> Writer {
+        let mut sink: Box<dyn Write + Send> = Box::new(BufWriter::with_capacity(batch_size * 2, sink));
+        if let Some(header) = generator.layout.header() {
+            let mut packed = Vec::new();
+            codec.compress_block(&header, level, &mut packed).expect("Failed to compress header");
+            if let Err(e) = sink.write_all(&packed) {
+                write_failed(e, &stop, &failed);
+            }
+        }
+        Writer::spawn(sink, threads * writer::BUFFERS_PER_WORKER, ordered)
+    };
+
+    // Consumers started with --exec: one shared child, or one per worker with its own stdin
+    let mut children = Vec::new();
+    let mut worker_outputs: Vec<Writer> = Vec::new();
+    if let Some(ref cmd) = exec_cmd {
+        let consumers = if exec_per_worker { threads } else { 1 };
+        for w in 0..consumers {
+            let (child, stdin) = exec::spawn(cmd, exec_per_worker.then_some(w)).unwrap_or_else(|e| {
+                eprintln!("Error: failed to start '{}': {}", cmd, e);
+                std::process::exit(1);
+            });
+            children.push(child);
+            worker_outputs.push(open_writer(Box::new(stdin)));
+        }
+    }
+
+    let shared_output: Option<Writer> = if no_output || bin_header.is_some() || split_plan.is_some() || per_thread_dir.is_some() {
         None
+    } else if exec_cmd.is_some() {
+        if exec_per_worker { None } else { worker_outputs.pop() }
+    } else if to_stdout {
+        Some(open_writer(Box::new(std::io::stdout())))
     } else {
-        let file = File::create(&output_path).expect("Failed to create output file");
-        let writer: Box<dyn Write + Send> = if compress {
-            Box::new(BufWriter::with_capacity(batch_size * 2, GzEncoder::new(file, Compression::fast())))
-        } else {
-            Box::new(BufWriter::with_capacity(batch_size * 2, file))
-        };
-        Some(Arc::new(Mutex::new(writer)))
+        match File::create(&output_path) {
+            Ok(file) => Some(open_writer(Box::new(file))),
+            Err(e) => { write_failed(path_error(&output_path, e), &stop, &failed); None }
+        }
     };
 
     let memory_storage: Option<Arc<Mutex<Vec<Vec<u8>>>>> = if memory_only {
@@ -244,138 +1332,334 @@ pub fn main() {
         None
     };
 
-    let mut handles = Vec::with_capacity(threads);
-    let mut current_index = start_index;
 
-    const PROGRESS_BATCH: u64 = 50_000;
-    const WRITE_THRESHOLD: usize = 1024 * 1024; // 1MB before write
+    let search_state: Option<Arc<SearchState>> = target.as_ref().map(|_| {
+        Arc::new(SearchState::new(&vec![start_index; threads], effective_total))
+    });
 
-    for tid in 0..threads {
-        let count = per_thread + if remainder > 0 { remainder -= 1; 1 } else { 0 };
-        if count == 0 { break; }
-        let start = current_index;
-        current_index += count;
+    let mut handles = Vec::with_capacity(threads);
 
-        let charset_local = charset.clone();
+    for tid in 0..threads {
+        let gen = Arc::clone(&generator);
+        let sched = Arc::clone(&scheduler);
         let produced_clone = Arc::clone(&produced);
-        let resume_counter_clone = Arc::clone(&resume_counter);
-        let pb_clone = pb.clone();
-        let output_clone = output_arc.clone();
+        let stop_clone = Arc::clone(&stop);
+        let failed_clone = Arc::clone(&failed);
+        let written_clone = Arc::clone(&written);
+        let excluded_clone = Arc::clone(&excluded);
+        let split_clone = split_plan.clone();
+        let per_thread_clone = per_thread_dir.clone();
+        let per_thread_done = Arc::clone(&per_thread_parts);
+        let mut progress = Progress { pb: pb.clone(), pending: 0 };
+        let sender = worker_outputs.get(tid).or(shared_output.as_ref()).map(|w| w.sender(writer::BUFFERS_PER_WORKER, batch_size + record_len));
+        let bin_clone = bin_header.clone();
+        let output_path_local = output_path.clone();
         let memory_clone = memory_storage.clone();
+        let target_clone = target.clone();
+        let search_clone = search_state.clone();
 
         handles.push(thread::spawn(move || {
-            let mut digits = vec![0u32; length];
-            index_to_digits(start, base, &mut digits);
-            let base_u32 = base as u32;
-            
-            let mut buf = Vec::with_capacity(batch_size + length + 1);
+            let mut cursor = Cursor::new(&gen, start_index);
+            let mut buf = Vec::with_capacity(batch_size + record_len);
+            let mut packed = Vec::new();
             let mut local_count = 0u64;
-            let mut progress_acc = 0u64;
-
-            if memory_clone.is_some() {
-                let mut local_memory = Vec::with_capacity((count as usize).min(100_000));
-                for _ in 0..count {
-                    let combo: Vec<u8> = digits.iter().map(|&d| charset_local[d as usize]).collect();
-                    local_memory.push(combo);
-                    odometer_increment(&mut digits, base_u32);
-                    local_count += 1;
-                    progress_acc += 1;
-                    if progress_acc >= PROGRESS_BATCH {
-                        pb_clone.inc(progress_acc);
-                        resume_counter_clone.fetch_add(progress_acc, Ordering::Relaxed);
-                        progress_acc = 0;
+
+            if let Some(ref split) = split_clone {
+                // Every chunk is one part file; parts finished before a failure still go into the manifest
+                while let Some(chunk) = sched.next() {
+                    if stop_clone.load(Ordering::Relaxed) { break; }
+                    let part = chunk.range.start / split.per_part;
+                    let path = parts::part_path(&output_path_local, part, split.width);
+                    let header = if part == 0 { gen.layout.header() } else { None };
+                    cursor.seek(chunk.range.start);
+                    let info = match write_part(&mut cursor, chunk.range.end, part, &path, header.as_deref(), codec, level,
+                                                &mut buf, &mut packed, &mut progress, &written_clone) {
+                        Ok(info) => info,
+                        Err(e) => { write_failed(path_error(&path, e), &stop_clone, &failed_clone); break; }
+                    };
+                    local_count += chunk.range.end - chunk.range.start;
+                    split.done.lock().unwrap().push(info);
+                    sched.complete(chunk.seq);
+                }
+            } else if let Some(ref dir) = per_thread_clone {
+                // One chunk per file, so a worker normally writes a single file without a shared lock
+                while let Some(chunk) = sched.next() {
+                    if stop_clone.load(Ordering::Relaxed) { break; }
+                    let part = first_per_thread + chunk.seq;
+                    let path = parts::part_path(&format!("{}/{}", dir, parts::file_name(&output_path_local)), part, width_per_thread);
+                    let header = if chunk.range.start == 0 { gen.layout.header() } else { None };
+                    cursor.seek(chunk.range.start);
+                    let info = match write_part(&mut cursor, chunk.range.end, part, &path, header.as_deref(), codec, level,
+                                                &mut buf, &mut packed, &mut progress, &written_clone) {
+                        Ok(info) => info,
+                        Err(e) => { write_failed(path_error(&path, e), &stop_clone, &failed_clone); break; }
+                    };
+                    local_count += chunk.range.end - chunk.range.start;
+                    per_thread_done.lock().unwrap().push(info);
+                    sched.complete(chunk.seq);
+                }
+            } else if let (Some(target), Some(state)) = (&target_clone, &search_clone) {
+                // Candidates are tested in place and never leave the worker
+                'chunks: while let Some(chunk) = sched.next() {
+                    cursor.seek(chunk.range.start);
+                    for n in 0..chunk.range.end - chunk.range.start {
+                        if n % search::CHECK_INTERVAL == 0 {
+                            if state.is_done() { break 'chunks; }
+                            state.set_position(tid, cursor.index);
+                        }
+                        buf.clear();
+                        cursor.render(&mut buf);
+                        local_count += 1;
+                        progress.tick();
+                        if !cursor.is_excluded(&buf) && target.matches(&buf) {
+                            state.report(cursor.index, &buf);
+                            break 'chunks;
+                        }
+                        cursor.advance();
                     }
+                    sched.complete(chunk.seq);
                 }
-                let mut storage = memory_clone.as_ref().unwrap().lock().unwrap();
+                if !state.is_done() { state.finish(tid); }
+                buf.clear();
+            } else if let Some(ref memory) = memory_clone {
+                let mut local_memory = Vec::new();
+                while let Some(chunk) = sched.next() {
+                    cursor.seek(chunk.range.start);
+                    for _ in chunk.range.clone() {
+                        let mut combo = Vec::with_capacity(candidate_len);
+                        cursor.render(&mut combo);
+                        if !cursor.is_excluded(&combo) {
+                            local_memory.push(combo);
+                        }
+                        cursor.advance();
+                        local_count += 1;
+                        progress.tick();
+                    }
+                    sched.complete(chunk.seq);
+                }
+                let mut storage = memory.lock().unwrap();
                 storage.extend(local_memory);
-            } else if dry_run {
-                for _ in 0..count {
-                    odometer_increment(&mut digits, base_u32);
-                    local_count += 1;
+            } else if let Some(ref header) = bin_clone {
+                // Records sit at fixed offsets, so every chunk goes straight to its own region of the file
+                if !stop_clone.load(Ordering::Relaxed) {
+                    let result = OpenOptions::new().write(true).open(&output_path_local).and_then(|mut file| {
+                        while let Some(chunk) = sched.next() {
+                            if stop_clone.load(Ordering::Relaxed) { break; }
+                            write_binary_chunk(&mut file, header, &mut cursor, chunk.range.clone(), &mut buf, &mut progress, &written_clone)?;
+                            local_count += chunk.range.end - chunk.range.start;
+                            sched.complete(chunk.seq);
+                        }
+                        Ok(())
+                    });
+                    if let Err(e) = result {
+                        write_failed(path_error(&output_path_local, e), &stop_clone, &failed_clone);
+                    }
                 }
-                pb_clone.inc(count);
-            } else {
-                for _ in 0..count {
-                    generate_combo_fast(&digits, &charset_local, &mut buf);
-                    local_count += 1;
-                    progress_acc += 1;
-
-                    if progress_acc >= PROGRESS_BATCH {
-                        pb_clone.inc(progress_acc);
-                        resume_counter_clone.fetch_add(progress_acc, Ordering::Relaxed);
-                        progress_acc = 0;
+            } else if dry_run {
+                while let Some(chunk) = sched.next() {
+                    let count = chunk.range.end - chunk.range.start;
+                    cursor.seek(chunk.range.start);
+                    for _ in 0..count {
+                        if gen.exclude.is_some() {
+                            buf.clear();
+                            cursor.emit(&mut buf);
+                        }
+                        cursor.advance();
                     }
+                    local_count += count;
+                    progress.pb.inc(count);
+                    sched.complete(chunk.seq);
+                }
+            } else if let Some(mut sender) = sender {
+                // Filled buffers go to the writer thread, chunks count as done once all their records are handed over.
+                // Ordered output sends every chunk as its own blocks, closed with a `last` one
+                let mut buffered = Vec::new();
+                'chunks: while let Some(chunk) = sched.next() {
+                    cursor.seek(chunk.range.start);
+                    while cursor.index < chunk.range.end {
+                        let n = cursor.emit_run(&mut buf, chunk.range.end - cursor.index);
+                        local_count += n;
+                        progress.add(n);
 
-                    if buf.len() >= WRITE_THRESHOLD {
-                        if let Some(ref out) = output_clone {
-                            let mut w = out.lock().unwrap();
-                            let _ = w.write_all(&buf);
+                        if buf.len() >= WRITE_THRESHOLD {
+                            if let Err(e) = send_block(&mut sender, chunk.seq, false, codec, level, &mut buf, &written_clone) {
+                                write_failed(e, &stop_clone, &failed_clone);
+                            }
+                            if stop_clone.load(Ordering::Relaxed) {
+                                if ordered { let _ = sender.send(chunk.seq, true, Vec::new()); }
+                                break 'chunks;
+                            }
+                            for seq in buffered.drain(..) { sched.complete(seq); }
                         }
-                        buf.clear();
                     }
-
-                    odometer_increment(&mut digits, base_u32);
+                    if ordered {
+                        if let Err(e) = send_block(&mut sender, chunk.seq, true, codec, level, &mut buf, &written_clone) {
+                            write_failed(e, &stop_clone, &failed_clone);
+                        }
+                        if stop_clone.load(Ordering::Relaxed) { break; }
+                        sched.complete(chunk.seq);
+                    } else {
+                        buffered.push(chunk.seq);
+                    }
                 }
 
-                if !buf.is_empty() {
-                    if let Some(ref out) = output_clone {
-                        let mut w = out.lock().unwrap();
-                        let _ = w.write_all(&buf);
+                if !buf.is_empty() && !stop_clone.load(Ordering::Relaxed) {
+                    if let Err(e) = send_block(&mut sender, 0, false, codec, level, &mut buf, &written_clone) {
+                        write_failed(e, &stop_clone, &failed_clone);
                     }
                 }
+                if !stop_clone.load(Ordering::Relaxed) {
+                    for seq in buffered { sched.complete(seq); }
+                }
             }
 
-            if progress_acc > 0 {
-                pb_clone.inc(progress_acc);
-                resume_counter_clone.fetch_add(progress_acc, Ordering::Relaxed);
-            }
+            progress.flush();
 
             produced_clone.fetch_add(local_count, Ordering::Relaxed);
-            if verbose { println!("Thread {} done: {}", tid, local_count); }
+            excluded_clone.fetch_add(cursor.excluded, Ordering::Relaxed);
+            if verbose { status!("Thread {} done: {}", tid, local_count); }
         }));
     }
 
+    // The index below which every chunk is finished (searches track positions inside chunks)
+    let checkpoint = || match search_state {
+        Some(ref state) => state.checkpoint(),
+        None => scheduler.checkpoint(),
+    };
+
+    // While workers run: a consumer exiting with 0 means it found what it was
+    // looking for, and the checkpoint is saved for --resume
+    let mut consumer_success = false;
+    let mut last_checkpoint = Instant::now();
+    while (exec_stop_on_success || resume_file.is_some()) && !handles.iter().all(|h| h.is_finished()) {
+        for child in children.iter_mut() {
+            if let Ok(Some(status)) = child.try_wait() {
+                if status.success() {
+                    consumer_success = true;
+                    stop.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+        if let Some(ref resume) = resume_file {
+            if last_checkpoint.elapsed() >= CHECKPOINT_INTERVAL {
+                let _ = std::fs::write(resume, checkpoint().to_string());
+                last_checkpoint = Instant::now();
+            }
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+
     for h in handles { h.join().expect("Thread panicked"); }
 
-    if let Some(out) = output_arc {
-        let mut w = out.lock().unwrap();
-        w.flush().expect("Failed to flush");
+    for out in shared_output.into_iter().chain(worker_outputs) {
+        if let Err(e) = out.finish() {
+            write_failed(e, &stop, &failed);
+        }
+    }
+
+    // Closing stdin lets consumers see EOF, then their exit status becomes ours
+    let mut consumer_code = 0;
+    for mut child in children {
+        let status = match child.try_wait() {
+            Ok(Some(status)) => status,
+            _ if consumer_success => { let _ = child.kill(); child.wait().expect("Failed to wait for consumer") }
+            _ => child.wait().expect("Failed to wait for consumer"),
+        };
+        if status.success() && exec_stop_on_success {
+            consumer_success = true;
+        } else if !status.success() && consumer_code == 0 {
+            consumer_code = exec::exit_code(status);
+        }
+    }
+    if consumer_success {
+        status!("Consumer exited successfully, generation stopped");
+        consumer_code = 0;
+    }
+
+    if let Some(ref split) = split_plan {
+        // Parts from earlier runs stay listed, regenerated parts replace their entries
+        let manifest_path = parts::manifest_path(&output_path);
+        let mut manifest = if start_index > 0 { Manifest::read(&manifest_path).unwrap_or_default() } else { Manifest::default() };
+        manifest.range = Some((0, effective_total));
+        for info in split.done.lock().unwrap().drain(..) {
+            manifest.insert(info);
+        }
+        manifest.write(&manifest_path).expect("Failed to write manifest");
+        status!("Manifest: {} ({} parts)", manifest_path, manifest.parts.len());
+    }
+
+    if let Some(ref dir) = per_thread_dir {
+        let mut manifest = per_thread_manifest;
+        for info in per_thread_parts.lock().unwrap().drain(..) {
+            manifest.insert(info);
+        }
+        let manifest_path = parts::manifest_path(&format!("{}/{}", dir, parts::file_name(&output_path)));
+        manifest.write(&manifest_path).expect("Failed to write manifest");
+        status!("Manifest: {} (merge with: combo_gen merge {} <output>)", manifest_path, manifest_path);
     }
 
     if let Some(ref resume) = resume_file {
-        let final_idx = resume_counter.load(Ordering::Relaxed);
-        let _ = std::fs::write(resume, final_idx.to_string());
+        let _ = std::fs::write(resume, checkpoint().to_string());
     }
 
     pb.finish_with_message("✅ Complete!");
     let elapsed = start_time.elapsed().as_secs_f64();
     let total_done = produced.load(Ordering::Relaxed);
+    let total_excluded = excluded.load(Ordering::Relaxed);
 
-    println!("\n╔═══════════════════════════════════════╗");
-    println!("║          Performance Report           ║");
-    println!("╚═══════════════════════════════════════╝");
-    println!("Generated: {:>20}", format_number(total_done));
+    status!("\n╔═══════════════════════════════════════╗");
+    status!("║          Performance Report           ║");
+    status!("╚═══════════════════════════════════════╝");
+    status!("Generated: {:>20}", format_number(total_done - total_excluded));
+    if exclude_path.is_some() {
+        status!("Excluded: {:>21}", format_number(total_excluded));
+    }
     if start_index > 0 {
-        println!("Resumed from: {:>18}", format_number(start_index));
+        status!("Resumed from: {:>18}", format_number(start_index));
     }
-    println!("Time: {:>25.3} s", elapsed);
-    println!("Throughput: {:>17.2} M/s", total_done as f64 / elapsed / 1_000_000.0);
+    status!("Time: {:>25.3} s", elapsed);
+    status!("Throughput: {:>17.2} M/s", total_done as f64 / elapsed / 1_000_000.0);
     
-    let bytes_written = total_done * (length + 1) as u64;
-    println!("Data written: {:>19}", format_bytes(bytes_written));
-    println!("Write speed: {:>18.2} MB/s", bytes_written as f64 / elapsed / 1_048_576.0);
-    println!("╚═══════════════════════════════════════╝");
+    // Dry-run and memory modes report the size the output would have had
+    let bytes_written = match written.load(Ordering::Relaxed) {
+        0 => (total_done - total_excluded) * record_len as u64,
+        n => n,
+    };
+    if search_state.is_none() {
+        status!("Data written: {:>19}", format_bytes(bytes_written));
+        status!("Write speed: {:>18.2} MB/s", bytes_written as f64 / elapsed / 1_048_576.0);
+    }
+    status!("╚═══════════════════════════════════════╝");
 
     if let Some(storage) = memory_storage {
         let data = storage.lock().unwrap();
-        println!("\nStored in memory: {} combinations", data.len());
+        status!("\nStored in memory: {} combinations", data.len());
         if verbose && !data.is_empty() {
-            println!("Samples:");
+            status!("Samples:");
             for (i, combo) in data.iter().take(5).enumerate() {
-                println!("  {}: {}", i + 1, String::from_utf8_lossy(combo));
+                status!("  {}: {}", i + 1, String::from_utf8_lossy(combo));
+            }
+        }
+    }
+
+    // Search result on stdout; like grep, exit code 1 means nothing matched
+    if let Some(state) = search_state {
+        match state.found() {
+            Some(found) => println!("Found at index {}: {}", found.index, String::from_utf8_lossy(&found.candidate)),
+            None => {
+                status!("No match in indices {}..{}", start_index, effective_total);
+                std::process::exit(1);
             }
         }
     }
+
+    if failed.load(Ordering::Relaxed) {
+        std::process::exit(1);
+    }
+    if consumer_code != 0 {
+        eprintln!("Consumer exited with code {}", consumer_code);
+        std::process::exit(consumer_code);
+    }
 }
 
 fn format_number(n: u64) -> String {
@@ -408,3 +1692,82 @@ fn format_bytes(bytes: u64) -> String {
         format!("{} B", bytes)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generator(source: Source, charset: &[u8], length: usize, framing: Framing, incremental: bool) -> Generator {
+        let base = charset.len() as u64;
+        let total = match source {
+            Source::Spaces(ref list) => list.total(),
+            _ => pow_u64(base, length).unwrap(),
+        };
+        Generator {
+            source,
+            charset: charset.to_vec(),
+            base,
+            length,
+            body: Framing { separator: framing.separator.clone(), ..Framing::default() },
+            framing,
+            layout: RecordLayout::default(),
+            plain: true,
+            exclude: None,
+            rules: None,
+            reverse: false,
+            total,
+            incremental,
+        }
+    }
+
+    // Records of `start..end` the way a worker writes one chunk
+    fn emit_chunk(cursor: &mut Cursor, start: u64, end: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        cursor.seek(start);
+        while cursor.index < end {
+            cursor.emit_run(&mut out, end - cursor.index);
+        }
+        out
+    }
+
+    #[test]
+    fn incremental_records_match_rendered_ones() {
+        let framing = Framing { prefix: b"<".to_vec(), suffix: b">".to_vec(), separator: b"-".to_vec() };
+        let fast = generator(Source::Charset, b"abc", 4, framing.clone(), true);
+        let slow = generator(Source::Charset, b"abc", 4, framing, false);
+        let mut expected = Vec::new();
+        let mut digits = vec![0; 4];
+        for i in 0..fast.total {
+            let mut record = Vec::new();
+            index_to_digits(i, 3, &mut digits);
+            generate_combo_fast(&digits, b"abc", &fast.framing, &mut record);
+            record.push(b'\n');
+            expected.push(record);
+        }
+        // Chunks starting mid-sweep, on carries and on the last record, in any order
+        let mut cursor = Cursor::new(&fast, 0);
+        let mut reference = Cursor::new(&slow, 0);
+        for (start, end) in [(0, 81), (1, 2), (2, 4), (5, 27), (26, 28), (8, 9), (53, 80), (80, 81), (40, 41)] {
+            let got = emit_chunk(&mut cursor, start, end);
+            assert_eq!(got, expected[start as usize..end as usize].concat(), "chunk {}..{}", start, end);
+            assert_eq!(got, emit_chunk(&mut reference, start, end), "chunk {}..{}", start, end);
+        }
+    }
+
+    #[test]
+    fn mixed_radix_records_match_keyspace() {
+        let ks = Keyspace::new(vec![Slot::from_charset(b"ab"), Slot::literal(b"-"), Slot::from_tokens(["x", "yz", "w"]), Slot::from_charset(b"0123")]);
+        let gen = generator(Source::Spaces(KeyspaceList::new(vec![ks.clone()]).unwrap()), b"", 0, Framing::default(), false);
+        let mut cursor = Cursor::new(&gen, 0);
+        for (start, end) in [(0, 24), (3, 9), (11, 13), (23, 24)] {
+            let mut expected = Vec::new();
+            let mut digits = vec![0; ks.len()];
+            for i in start..end {
+                ks.index_to_digits(i, &mut digits);
+                ks.render(&digits, b"", &mut expected);
+                expected.push(b'\n');
+            }
+            assert_eq!(emit_chunk(&mut cursor, start, end), expected, "chunk {}..{}", start, end);
+        }
+    }
+}
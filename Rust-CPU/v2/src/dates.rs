@@ -0,0 +1,409 @@
+// dates.rs - Calendar date strings (birthdays, anniversaries, expiry dates)
+//
+// Only real days are enumerated: each format walks every day from `from` to
+// `to` inclusive, so 31022000 or 29021900 are never produced. Formats come one
+// after another, so index = format * days + day offset.
+
+use crate::keyspace::push_decimal;
+
+/// A calendar day in the proleptic Gregorian calendar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: u32,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Parses `YYYY-MM-DD`, rejecting days that do not exist.
+    pub fn parse(s: &str) -> Result<Date, String> {
+        let bad = || format!("invalid date '{}' (expected YYYY-MM-DD)", s);
+        let mut parts = s.trim().splitn(3, '-');
+        let mut field = |max_digits: usize| -> Result<u32, String> {
+            let p = parts.next().ok_or_else(bad)?;
+            if p.is_empty() || p.len() > max_digits || !p.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(bad());
+            }
+            p.parse().map_err(|_| bad())
+        };
+        let date = Date { year: field(4)?, month: field(2)?, day: field(2)? };
+        if date.year == 0 || date.month == 0 || date.month > 12 || date.day == 0 || date.day > days_in_month(date.year, date.month) {
+            return Err(format!("'{}' is not a calendar date", s));
+        }
+        Ok(date)
+    }
+
+    /// Days since 0000-03-01, a day count without gaps between any two dates.
+    pub fn to_days(&self) -> u64 {
+        // Years start in March so the leap day is the last day of the year
+        let (y, m) = if self.month <= 2 { (self.year as u64 - 1, self.month + 9) } else { (self.year as u64, self.month - 3) };
+        let day_of_year = (153 * m as u64 + 2) / 5 + self.day as u64 - 1;
+        y * 365 + y / 4 - y / 100 + y / 400 + day_of_year
+    }
+
+    /// Inverse of `to_days`.
+    pub fn from_days(days: u64) -> Date {
+        let era = days / 146_097;
+        let day_of_era = days % 146_097;
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let m = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * m + 2) / 5 + 1) as u32;
+        let month = if m < 10 { m + 3 } else { m - 9 } as u32;
+        let year = (era * 400 + year_of_era) as u32 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+
+    /// Moves to the following day.
+    #[inline]
+    pub fn succ(&mut self) {
+        if self.day < days_in_month(self.year, self.month) {
+            self.day += 1;
+        } else if self.month < 12 {
+            self.month += 1;
+            self.day = 1;
+        } else {
+            *self = Date { year: self.year + 1, month: 1, day: 1 };
+        }
+    }
+
+    /// Moves to the preceding day.
+    #[inline]
+    pub fn pred(&mut self) {
+        if self.day > 1 {
+            self.day -= 1;
+        } else if self.month > 1 {
+            self.month -= 1;
+            self.day = days_in_month(self.year, self.month);
+        } else {
+            *self = Date { year: self.year - 1, month: 12, day: 31 };
+        }
+    }
+}
+
+pub fn is_leap_year(year: u32) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+pub fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Field {
+    /// Four-digit year
+    Year,
+    /// Last two digits of the year
+    ShortYear,
+    /// Month, zero padded to `width`
+    Month(usize),
+    /// Day of the month, zero padded to `width`
+    Day(usize),
+    Literal(Vec<u8>),
+}
+
+/// A date layout such as `DDMMYYYY`, `YYYY-MM-DD` or `D.M.YY`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DateFormat {
+    src: String,
+    fields: Vec<Field>,
+}
+
+impl DateFormat {
+    /// `YYYY`/`YY` year, `MM`/`M` month, `DD`/`D` day (single letters are not
+    /// padded); everything else is copied literally. Each of day, month and
+    /// year must appear exactly once, and an unpadded `M` or `D` needs a literal
+    /// between it and the neighbouring fields (`DMYYYY` renders both 1 Nov and
+    /// 11 Jan 2000 as 1112000). `YY` repeats every century, see `DateSpace::new`.
+    pub fn parse(src: &str) -> Result<DateFormat, String> {
+        let bytes = src.as_bytes();
+        let mut fields: Vec<Field> = Vec::new();
+        let (mut years, mut months, mut days) = (0, 0, 0);
+        let mut i = 0;
+        while i < bytes.len() {
+            let c = bytes[i].to_ascii_uppercase();
+            let run = bytes[i..].iter().take_while(|b| b.to_ascii_uppercase() == c).count();
+            let field = match (c, run) {
+                (b'Y', 4) => { years += 1; Field::Year }
+                (b'Y', 2) => { years += 1; Field::ShortYear }
+                (b'M', 1 | 2) => { months += 1; Field::Month(run) }
+                (b'D', 1 | 2) => { days += 1; Field::Day(run) }
+                (b'Y' | b'M' | b'D', _) => {
+                    return Err(format!("date format '{}': '{}' is not a field (YYYY, YY, MM, M, DD, D)", src, &src[i..i + run]))
+                }
+                _ => {
+                    match fields.last_mut() {
+                        Some(Field::Literal(lit)) => lit.push(bytes[i]),
+                        _ => fields.push(Field::Literal(vec![bytes[i]])),
+                    }
+                    i += 1;
+                    continue;
+                }
+            };
+            fields.push(field);
+            i += run;
+        }
+        if years != 1 || months != 1 || days != 1 {
+            return Err(format!("date format '{}' must contain one year, one month and one day field", src));
+        }
+        let unpadded = |f: &Field| matches!(f, Field::Month(1) | Field::Day(1));
+        let is_literal = |f: &Field| matches!(f, Field::Literal(_));
+        if fields.windows(2).any(|w| !is_literal(&w[0]) && !is_literal(&w[1]) && (unpadded(&w[0]) || unpadded(&w[1]))) {
+            return Err(format!("date format '{}': unpadded M and D must be separated from other fields (e.g. D.M.YYYY)", src));
+        }
+        Ok(DateFormat { src: src.to_string(), fields })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.src
+    }
+
+    fn has_short_year(&self) -> bool {
+        self.fields.contains(&Field::ShortYear)
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|f| match f {
+                Field::Year => 4,
+                Field::ShortYear | Field::Month(_) | Field::Day(_) => 2,
+                Field::Literal(lit) => lit.len(),
+            })
+            .sum()
+    }
+
+    #[inline]
+    pub fn render(&self, date: &Date, out: &mut Vec<u8>) {
+        for f in &self.fields {
+            match f {
+                Field::Year => push_decimal(date.year as u64, 4, out),
+                Field::ShortYear => push_decimal(date.year as u64 % 100, 2, out),
+                Field::Month(width) => push_decimal(date.month as u64, *width, out),
+                Field::Day(width) => push_decimal(date.day as u64, *width, out),
+                Field::Literal(lit) => out.extend_from_slice(lit),
+            }
+        }
+    }
+}
+
+/// Position of a worker in a `DateSpace`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DateState {
+    format: usize,
+    /// Days since `from`.
+    offset: u64,
+    date: Date,
+}
+
+/// Every day of `from..=to` in every format.
+pub struct DateSpace {
+    from: Date,
+    to: Date,
+    days: u64,
+    formats: Vec<DateFormat>,
+}
+
+impl DateSpace {
+    pub fn new(from: Date, to: Date, formats: Vec<DateFormat>) -> Result<DateSpace, String> {
+        if from > to {
+            return Err(format!("date range is empty: {:04}-{:02}-{:02} is after {:04}-{:02}-{:02}", from.year, from.month, from.day, to.year, to.month, to.day));
+        }
+        if formats.is_empty() {
+            return Err("at least one date format is required".to_string());
+        }
+        // Two-digit years repeat after a century, so longer ranges would render the same day twice
+        if to.year - from.year >= 100 {
+            if let Some(f) = formats.iter().find(|f| f.has_short_year()) {
+                return Err(format!("date format '{}' uses YY, which is ambiguous for ranges spanning 100 years or more", f.as_str()));
+            }
+        }
+        let days = to.to_days() - from.to_days() + 1;
+        Ok(DateSpace { from, to, days, formats })
+    }
+
+    pub fn from(&self) -> Date {
+        self.from
+    }
+
+    pub fn to(&self) -> Date {
+        self.to
+    }
+
+    pub fn days(&self) -> u64 {
+        self.days
+    }
+
+    pub fn formats(&self) -> &[DateFormat] {
+        &self.formats
+    }
+
+    pub fn total(&self) -> u64 {
+        self.days * self.formats.len() as u64
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.formats.iter().map(DateFormat::max_len).max().unwrap_or(0)
+    }
+
+    /// Positions `state` on candidate `index` (< total).
+    pub fn seek(&self, index: u64, state: &mut DateState) {
+        state.format = (index / self.days) as usize;
+        state.offset = index % self.days;
+        state.date = Date::from_days(self.from.to_days() + state.offset);
+    }
+
+    /// Moves to the next candidate; returns true when wrapping around to the first.
+    #[inline]
+    pub fn next(&self, state: &mut DateState) -> bool {
+        state.offset += 1;
+        if state.offset < self.days {
+            state.date.succ();
+            return false;
+        }
+        state.offset = 0;
+        state.date = self.from;
+        state.format += 1;
+        if state.format == self.formats.len() {
+            state.format = 0;
+            return true;
+        }
+        false
+    }
+
+    /// Moves to the previous candidate; returns true when wrapping around to the last.
+    #[inline]
+    pub fn prev(&self, state: &mut DateState) -> bool {
+        if state.offset > 0 {
+            state.offset -= 1;
+            state.date.pred();
+            return false;
+        }
+        state.offset = self.days - 1;
+        state.date = self.to;
+        if state.format > 0 {
+            state.format -= 1;
+            return false;
+        }
+        state.format = self.formats.len() - 1;
+        true
+    }
+
+    #[inline]
+    pub fn render(&self, state: &DateState, out: &mut Vec<u8>) {
+        self.formats[state.format].render(&state.date, out);
+    }
+}
+
+/// Parses `a..b` (exclusive) or `a..=b` (inclusive) into `(start, count)`.
+pub fn parse_range(s: &str) -> Result<(u64, u64), String> {
+    let bad = || format!("invalid range '{}' (expected a..b or a..=b)", s);
+    let (a, b) = s.trim().split_once("..").ok_or_else(bad)?;
+    let (b, inclusive) = match b.strip_prefix('=') {
+        Some(b) => (b, true),
+        None => (b, false),
+    };
+    let start: u64 = a.trim().parse().map_err(|_| bad())?;
+    let end: u64 = b.trim().parse().map_err(|_| bad())?;
+    let count = if inclusive { end.checked_sub(start).and_then(|n| n.checked_add(1)) } else { end.checked_sub(start) };
+    match count {
+        Some(n) if n > 0 => Ok((start, n)),
+        _ => Err(format!("range '{}' is empty", s)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> Date {
+        Date::parse(s).unwrap()
+    }
+
+    #[test]
+    fn day_numbers_round_trip_through_leap_days() {
+        // 1600 and 2000 are leap years, 1700, 1800, 1900 and 2100 are not
+        let mut d = date("1599-12-31");
+        let mut days = d.to_days();
+        while d < date("2401-03-01") {
+            let mut next = d;
+            next.succ();
+            assert_eq!(next.to_days(), days + 1, "after {:?}", d);
+            assert_eq!(Date::from_days(days + 1), next);
+            let mut back = next;
+            back.pred();
+            assert_eq!(back, d);
+            d = next;
+            days += 1;
+        }
+        assert_eq!(date("2001-01-01").to_days() - date("1901-01-01").to_days(), 36_525);
+        assert_eq!(date("2101-01-01").to_days() - date("2001-01-01").to_days(), 36_524);
+    }
+
+    #[test]
+    fn only_calendar_dates_parse() {
+        assert_eq!(date(" 2000-02-29 "), Date { year: 2000, month: 2, day: 29 });
+        assert_eq!(date("1-1-1"), Date { year: 1, month: 1, day: 1 });
+        for bad in ["2001-02-29", "1900-02-29", "2000-04-31", "2000-13-01", "0000-01-01", "2000-1", "2000-01-01x", "+200-01-01"] {
+            assert!(Date::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn formats_render_and_reject_ambiguous_layouts() {
+        let d = date("2004-03-07");
+        let render = |f: &str| {
+            let mut out = Vec::new();
+            DateFormat::parse(f).unwrap().render(&d, &mut out);
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(render("DDMMYYYY"), "07032004");
+        assert_eq!(render("yyyy-mm-dd"), "2004-03-07");
+        assert_eq!(render("D.M.YY"), "7.3.04");
+        for bad in ["DDMM", "DDMMYYYYYY", "DDDMMYY", "YYY-MM-DD", "DMYYYY", "DDMYYYY", "DD-MM-YY-YY"] {
+            assert!(DateFormat::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+        let yy = vec![DateFormat::parse("DDMMYY").unwrap()];
+        assert!(DateSpace::new(date("1950-01-01"), date("2049-12-31"), yy.clone()).is_ok());
+        assert!(DateSpace::new(date("1950-01-01"), date("2050-01-01"), yy).is_err());
+    }
+
+    #[test]
+    fn seek_matches_stepping() {
+        let formats = vec![DateFormat::parse("DDMMYYYY").unwrap(), DateFormat::parse("YYYY-M-D").unwrap()];
+        let space = DateSpace::new(date("1999-12-25"), date("2000-03-05"), formats).unwrap();
+        assert_eq!(space.total(), 2 * 72);
+        let render = |state: &DateState| {
+            let mut out = Vec::new();
+            space.render(state, &mut out);
+            out
+        };
+        let mut state = DateState::default();
+        space.seek(0, &mut state);
+        for index in 0..space.total() {
+            let mut sought = DateState::default();
+            space.seek(index, &mut sought);
+            assert_eq!(render(&state), render(&sought), "index {}", index);
+            let mut back = state;
+            let wrapped = space.prev(&mut back);
+            assert_eq!(wrapped, index == 0);
+            space.seek((index + space.total() - 1) % space.total(), &mut sought);
+            assert_eq!(render(&back), render(&sought), "before index {}", index);
+            assert_eq!(space.next(&mut state), index + 1 == space.total());
+        }
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(parse_range("5..8").unwrap(), (5, 3));
+        assert_eq!(parse_range(" 5..=8 ").unwrap(), (5, 4));
+        for bad in ["5..5", "8..5", "5", "..5", "a..b", "0..=18446744073709551615"] {
+            assert!(parse_range(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
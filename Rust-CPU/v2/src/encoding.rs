@@ -0,0 +1,212 @@
+// encoding.rs - Record encodings and delimiter parsing
+
+const HEX: &[u8; 16] = b"0123456789abcdef";
+const HEX_UPPER: &[u8; 16] = b"0123456789ABCDEF";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// How each record is encoded before the delimiter is appended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Raw,
+    Hex,
+    Base64,
+    Percent,
+    CEscape,
+}
+
+impl Encoding {
+    pub fn parse(name: &str) -> Result<Encoding, String> {
+        match name {
+            "raw" => Ok(Encoding::Raw),
+            "hex" => Ok(Encoding::Hex),
+            "base64" => Ok(Encoding::Base64),
+            "percent" | "url" => Ok(Encoding::Percent),
+            "c" | "cescape" => Ok(Encoding::CEscape),
+            _ => Err(format!("unknown encoding '{}' (raw, hex, base64, percent, c)", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Raw => "raw",
+            Encoding::Hex => "hex",
+            Encoding::Base64 => "base64",
+            Encoding::Percent => "percent",
+            Encoding::CEscape => "c",
+        }
+    }
+
+    /// Upper bound on the encoded size of `n` input bytes.
+    pub fn max_len(&self, n: usize) -> usize {
+        match self {
+            Encoding::Raw => n,
+            Encoding::Hex => n * 2,
+            Encoding::Base64 => n.div_ceil(3) * 4,
+            Encoding::Percent => n * 3,
+            Encoding::CEscape => n * 4,
+        }
+    }
+
+    #[inline]
+    pub fn encode(&self, input: &[u8], out: &mut Vec<u8>) {
+        match self {
+            Encoding::Raw => out.extend_from_slice(input),
+            Encoding::Hex => encode_hex(input, out),
+            Encoding::Base64 => encode_base64(input, out),
+            Encoding::Percent => {
+                for &b in input {
+                    if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+                        out.push(b);
+                    } else {
+                        out.extend_from_slice(&[b'%', HEX_UPPER[(b >> 4) as usize], HEX_UPPER[(b & 15) as usize]]);
+                    }
+                }
+            }
+            Encoding::CEscape => {
+                for &b in input {
+                    match b {
+                        b'\n' => out.extend_from_slice(b"\\n"),
+                        b'\r' => out.extend_from_slice(b"\\r"),
+                        b'\t' => out.extend_from_slice(b"\\t"),
+                        b'\\' => out.extend_from_slice(b"\\\\"),
+                        b'"' => out.extend_from_slice(b"\\\""),
+                        0x20..=0x7e => out.push(b),
+                        _ => out.extend_from_slice(&[b'\\', b'x', HEX[(b >> 4) as usize], HEX[(b & 15) as usize]]),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[inline]
+pub fn encode_hex(input: &[u8], out: &mut Vec<u8>) {
+    for &b in input {
+        out.push(HEX[(b >> 4) as usize]);
+        out.push(HEX[(b & 15) as usize]);
+    }
+}
+
+/// Writes the lowercase hex form of `input` into `out[..input.len() * 2]`.
+#[inline]
+pub fn hex_into(input: &[u8], out: &mut [u8]) {
+    for (pair, &b) in out.chunks_exact_mut(2).zip(input) {
+        pair[0] = HEX[(b >> 4) as usize];
+        pair[1] = HEX[(b & 15) as usize];
+    }
+}
+
+pub fn encode_base64(input: &[u8], out: &mut Vec<u8>) {
+    for chunk in input.chunks(3) {
+        let n = chunk.len();
+        let v = (chunk[0] as u32) << 16
+            | (if n > 1 { chunk[1] as u32 } else { 0 }) << 8
+            | (if n > 2 { chunk[2] as u32 } else { 0 });
+        out.push(BASE64[(v >> 18) as usize & 63]);
+        out.push(BASE64[(v >> 12) as usize & 63]);
+        out.push(if n > 1 { BASE64[(v >> 6) as usize & 63] } else { b'=' });
+        out.push(if n > 2 { BASE64[v as usize & 63] } else { b'=' });
+    }
+}
+
+pub fn decode_hex(s: &str) -> Result<Vec<u8>, String> {
+    let s = s.trim();
+    // from_str_radix would also take a sign, so the digits are checked first
+    if !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err("hex string contains non-hex characters".to_string());
+    }
+    if !s.len().is_multiple_of(2) {
+        return Err("hex string must have an even number of digits".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|_| format!("invalid hex byte '{}'", &s[i..i + 2])))
+        .collect()
+}
+
+/// Expands `\n`, `\r`, `\t`, `\0`, `\\` and `\xHH` escapes.
+pub fn unescape(s: &str) -> Result<Vec<u8>, String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'\\' {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        match bytes.get(i + 1) {
+            Some(b'n') => out.push(b'\n'),
+            Some(b'r') => out.push(b'\r'),
+            Some(b't') => out.push(b'\t'),
+            Some(b'0') => out.push(0),
+            Some(b'\\') => out.push(b'\\'),
+            Some(b'x') => {
+                let hex = s.get(i + 2..i + 4).ok_or("truncated \\x escape")?;
+                if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                    return Err(format!("invalid escape \\x{}", hex));
+                }
+                out.push(u8::from_str_radix(hex, 16).map_err(|_| format!("invalid escape \\x{}", hex))?);
+                i += 2;
+            }
+            Some(&c) => return Err(format!("unknown escape \\{}", c as char)),
+            None => return Err("dangling '\\' at end of string".to_string()),
+        }
+        i += 2;
+    }
+    Ok(out)
+}
+
+/// Record terminator by name (`lf`, `crlf`, `nul`, `none`) or as an escaped byte string.
+pub fn parse_delimiter(s: &str) -> Result<Vec<u8>, String> {
+    match s {
+        "lf" => Ok(b"\n".to_vec()),
+        "crlf" => Ok(b"\r\n".to_vec()),
+        "nul" => Ok(vec![0]),
+        "none" => Ok(Vec::new()),
+        _ => unescape(s),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(encoding: Encoding, input: &[u8]) -> String {
+        let mut out = Vec::new();
+        encoding.encode(input, &mut out);
+        assert!(out.len() <= encoding.max_len(input.len()));
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(encoded(Encoding::Hex, b"\x00az"), "00617a");
+        assert_eq!(encoded(Encoding::Base64, b"f"), "Zg==");
+        assert_eq!(encoded(Encoding::Base64, b"fo"), "Zm8=");
+        assert_eq!(encoded(Encoding::Base64, b"foobar"), "Zm9vYmFy");
+        assert_eq!(encoded(Encoding::Percent, b"a b/~\xff"), "a%20b%2F~%FF");
+        assert_eq!(encoded(Encoding::CEscape, b"a\"\\\n\x01"), "a\\\"\\\\\\n\\x01");
+    }
+
+    #[test]
+    fn hex_decoding() {
+        assert_eq!(decode_hex(" 00ff7A ").unwrap(), [0x00, 0xff, 0x7a]);
+        assert_eq!(decode_hex("").unwrap(), []);
+        for bad in ["abc", "zz", "+1", "é1"] {
+            assert!(decode_hex(bad).is_err(), "{:?} should not decode", bad);
+        }
+    }
+
+    #[test]
+    fn delimiters() {
+        assert_eq!(parse_delimiter("lf").unwrap(), b"\n");
+        assert_eq!(parse_delimiter("crlf").unwrap(), b"\r\n");
+        assert_eq!(parse_delimiter("nul").unwrap(), [0]);
+        assert_eq!(parse_delimiter("none").unwrap(), []);
+        assert_eq!(parse_delimiter("\\t|\\x1e\\\\").unwrap(), b"\t|\x1e\\");
+        for bad in ["\\", "\\q", "\\x1", "\\x+1", "\\xzz"] {
+            assert!(parse_delimiter(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
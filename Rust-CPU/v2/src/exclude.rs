@@ -0,0 +1,99 @@
+// exclude.rs - Known candidates to skip (`--exclude wordlist.txt`)
+//
+// Words are kept sorted in one buffer for exact lookups by binary search. A
+// Bloom filter in front answers most misses without touching the words, so
+// candidates that are not excluded cost a hash and a few bit tests.
+
+use std::cmp::Ordering;
+use std::io;
+
+use crate::wordlist;
+
+const BLOOM_BITS_PER_WORD: usize = 10;
+const BLOOM_PROBES: u64 = 7;
+
+pub struct ExcludeSet {
+    data: Vec<u8>,
+    /// End offset of every word in `data`, in sorted order.
+    ends: Vec<usize>,
+    bloom: Vec<u64>,
+    bloom_mask: u64,
+}
+
+impl ExcludeSet {
+    pub fn load(path: &str) -> io::Result<ExcludeSet> {
+        let data = wordlist::read(path)?;
+        Ok(ExcludeSet::from_words(wordlist::lines(&data)))
+    }
+
+    pub fn from_words<'a, I: IntoIterator<Item = &'a [u8]>>(words: I) -> ExcludeSet {
+        let mut words: Vec<&[u8]> = words.into_iter().collect();
+        words.sort_unstable();
+        words.dedup();
+
+        let bits = (words.len() * BLOOM_BITS_PER_WORD).next_power_of_two().max(64);
+        let mut set = ExcludeSet {
+            data: Vec::with_capacity(words.iter().map(|w| w.len()).sum()),
+            ends: Vec::with_capacity(words.len()),
+            bloom: vec![0; bits / 64],
+            bloom_mask: bits as u64 - 1,
+        };
+        for w in words {
+            set.data.extend_from_slice(w);
+            set.ends.push(set.data.len());
+            let (h1, h2) = hash_pair(w);
+            for i in 0..BLOOM_PROBES {
+                let bit = h1.wrapping_add(i.wrapping_mul(h2)) & set.bloom_mask;
+                set.bloom[(bit / 64) as usize] |= 1 << (bit % 64);
+            }
+        }
+        set
+    }
+
+    pub fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ends.is_empty()
+    }
+
+    fn word(&self, i: usize) -> &[u8] {
+        let from = if i == 0 { 0 } else { self.ends[i - 1] };
+        &self.data[from..self.ends[i]]
+    }
+
+    #[inline]
+    pub fn contains(&self, candidate: &[u8]) -> bool {
+        let (h1, h2) = hash_pair(candidate);
+        for i in 0..BLOOM_PROBES {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) & self.bloom_mask;
+            if self.bloom[(bit / 64) as usize] & (1 << (bit % 64)) == 0 {
+                return false;
+            }
+        }
+        let (mut lo, mut hi) = (0, self.ends.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            match self.word(mid).cmp(candidate) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return true,
+            }
+        }
+        false
+    }
+}
+
+// FNV-1a with a final mix, split into the two hashes of double hashing
+#[inline]
+fn hash_pair(bytes: &[u8]) -> (u64, u64) {
+    let mut h: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        h = (h ^ b as u64).wrapping_mul(0x100000001b3);
+    }
+    h ^= h >> 33;
+    h = h.wrapping_mul(0xff51afd7ed558ccd);
+    h ^= h >> 33;
+    (h, (h >> 32) | 1)
+}
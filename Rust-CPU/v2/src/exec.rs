@@ -0,0 +1,40 @@
+// exec.rs - Child processes that consume combinations on stdin
+
+use std::io;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+
+/// Starts `command` through the platform shell with a piped stdin. The child
+/// inherits stdout and stderr. `worker` is exported as `COMBO_GEN_WORKER` so
+/// per-worker consumers can tell themselves apart.
+pub fn spawn(command: &str, worker: Option<usize>) -> io::Result<(Child, ChildStdin)> {
+    let mut cmd = if cfg!(windows) {
+        let mut c = Command::new("cmd");
+        c.arg("/C").arg(command);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.arg("-c").arg(command);
+        c
+    };
+    if let Some(w) = worker {
+        cmd.env("COMBO_GEN_WORKER", w.to_string());
+    }
+    let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+    let stdin = child.stdin.take().ok_or_else(|| io::Error::other("child stdin unavailable"))?;
+    Ok((child, stdin))
+}
+
+/// Exit code to propagate for a finished child; signals map to 128 + signal on Unix.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    if let Some(code) = status.code() {
+        return code;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(sig) = status.signal() {
+            return 128 + sig;
+        }
+    }
+    1
+}
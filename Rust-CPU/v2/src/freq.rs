@@ -0,0 +1,57 @@
+// freq.rs - Symbol frequency tables for --order-by frequency
+//
+// A table has one `<symbol> <count>` line per symbol, e.g. the output of a
+// character count over a leak. The symbol takes the \n, \t, \xHH escapes and
+// everything before the last run of whitespace, so a space is written as `  42`.
+// Blank lines and lines starting with `#` are ignored.
+
+use std::fs;
+use std::path::Path;
+
+use crate::encoding;
+
+/// Occurrence count per byte value.
+pub struct FrequencyTable {
+    counts: Box<[u64; 256]>,
+}
+
+impl FrequencyTable {
+    pub fn parse(text: &str) -> Result<FrequencyTable, String> {
+        let mut counts = Box::new([0u64; 256]);
+        for (n, line) in text.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = |why: &str| format!("line {}: {} in '{}'", n + 1, why, line);
+            let (symbol, count) = line.trim_end().rsplit_once([' ', '\t']).ok_or_else(|| bad("expected '<symbol> <count>'"))?;
+            let count: u64 = count.parse().map_err(|_| bad("invalid count"))?;
+            // Only the separating whitespace is dropped, the symbol may itself be a space
+            let symbol = match symbol.trim_end_matches([' ', '\t']) {
+                "" => &symbol[..1],
+                s => s,
+            };
+            match encoding::unescape(symbol).map_err(|e| bad(&e))?.as_slice() {
+                &[b] => counts[b as usize] = counts[b as usize].saturating_add(count),
+                _ => return Err(bad("symbol must be a single byte")),
+            }
+        }
+        Ok(FrequencyTable { counts })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<FrequencyTable, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        FrequencyTable::parse(&text)
+    }
+
+    pub fn count(&self, symbol: u8) -> u64 {
+        self.counts[symbol as usize]
+    }
+
+    /// `charset` with the most frequent symbols first. Symbols missing from the
+    /// table count as zero; ties keep their charset order.
+    pub fn sort_charset(&self, charset: &[u8]) -> Vec<u8> {
+        let mut sorted = charset.to_vec();
+        sorted.sort_by_key(|&c| std::cmp::Reverse(self.count(c)));
+        sorted
+    }
+}
@@ -0,0 +1,143 @@
+// hash.rs - Digests applied to candidates for `--hash` output and hash search
+//
+// Algorithms implement `HashAlgo`; `by_name` knows the built-in ones. Library
+// users can plug their own digest into `RecordLayout::hash` directly.
+
+use std::fmt;
+use std::sync::Arc;
+
+use md4::Md4;
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+/// Largest digest, in bytes, a `HashAlgo` may produce.
+pub const MAX_DIGEST: usize = 64;
+
+pub trait HashAlgo: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Digest size in bytes, at most `MAX_DIGEST`.
+    fn output_len(&self) -> usize;
+
+    /// Writes the digest of `input` into `out[..output_len()]`.
+    fn digest(&self, input: &[u8], out: &mut [u8]);
+}
+
+pub struct Md5Hash;
+pub struct Sha1Hash;
+pub struct Sha256Hash;
+
+/// NTLM: MD4 over the UTF-16LE form of the candidate. Bytes that are not
+/// valid UTF-8 are taken as Latin-1 code units.
+pub struct NtlmHash;
+
+impl HashAlgo for Md5Hash {
+    fn name(&self) -> &str { "md5" }
+    fn output_len(&self) -> usize { 16 }
+    fn digest(&self, input: &[u8], out: &mut [u8]) {
+        out[..16].copy_from_slice(&Md5::digest(input));
+    }
+}
+
+impl HashAlgo for Sha1Hash {
+    fn name(&self) -> &str { "sha1" }
+    fn output_len(&self) -> usize { 20 }
+    fn digest(&self, input: &[u8], out: &mut [u8]) {
+        out[..20].copy_from_slice(&Sha1::digest(input));
+    }
+}
+
+impl HashAlgo for Sha256Hash {
+    fn name(&self) -> &str { "sha256" }
+    fn output_len(&self) -> usize { 32 }
+    fn digest(&self, input: &[u8], out: &mut [u8]) {
+        out[..32].copy_from_slice(&Sha256::digest(input));
+    }
+}
+
+impl HashAlgo for NtlmHash {
+    fn name(&self) -> &str { "ntlm" }
+    fn output_len(&self) -> usize { 16 }
+    fn digest(&self, input: &[u8], out: &mut [u8]) {
+        let mut h = Md4::new();
+        let mut units = [0u8; 128];
+        let mut n = 0;
+        let mut push = |u: u16, h: &mut Md4| {
+            units[n..n + 2].copy_from_slice(&u.to_le_bytes());
+            n += 2;
+            if n == units.len() {
+                h.update(units);
+                n = 0;
+            }
+        };
+        match std::str::from_utf8(input) {
+            Ok(s) => s.encode_utf16().for_each(|u| push(u, &mut h)),
+            Err(_) => input.iter().for_each(|&b| push(b as u16, &mut h)),
+        }
+        h.update(&units[..n]);
+        out[..16].copy_from_slice(&h.finalize());
+    }
+}
+
+/// Built-in algorithm by name: md5, sha1, sha256, ntlm.
+pub fn by_name(name: &str) -> Result<Arc<dyn HashAlgo>, String> {
+    match name.to_ascii_lowercase().as_str() {
+        "md5" => Ok(Arc::new(Md5Hash)),
+        "sha1" | "sha-1" => Ok(Arc::new(Sha1Hash)),
+        "sha256" | "sha-256" => Ok(Arc::new(Sha256Hash)),
+        "ntlm" => Ok(Arc::new(NtlmHash)),
+        _ => Err(format!("unknown hash '{}' (md5, sha1, sha256, ntlm)", name)),
+    }
+}
+
+/// Hash column added to each record by `--hash`.
+#[derive(Clone)]
+pub struct HashOutput {
+    pub algo: Arc<dyn HashAlgo>,
+    /// Emit only the hash instead of `hash:plaintext`.
+    pub hashes_only: bool,
+}
+
+impl fmt::Debug for HashOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HashOutput").field("algo", &self.algo.name()).field("hashes_only", &self.hashes_only).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_digest(name: &str, input: &[u8]) -> String {
+        let algo = by_name(name).unwrap();
+        let mut out = vec![0u8; algo.output_len()];
+        algo.digest(input, &mut out);
+        out.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn known_digests() {
+        assert_eq!(hex_digest("md5", b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex_digest("md5", b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(hex_digest("sha1", b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hex_digest("SHA-256", b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(hex_digest("ntlm", b""), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex_digest("ntlm", b"password"), "8846f7eaee8fb117ad06bdd830b7586c");
+        assert!(by_name("crc32").is_err());
+    }
+
+    #[test]
+    fn ntlm_hashes_utf16_across_buffer_refills() {
+        // 152 UTF-16 units fill the 128-byte unit buffer twice over
+        let text = "pässwörd".repeat(19);
+        let units: Vec<u8> = text.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let expected: String = Md4::digest(&units).iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(hex_digest("ntlm", text.as_bytes()), expected);
+        // bytes that are not UTF-8 are taken as Latin-1
+        assert_eq!(hex_digest("ntlm", b"p\xe4ss"), hex_digest("ntlm", "päss".as_bytes()));
+    }
+}
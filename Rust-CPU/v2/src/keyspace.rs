@@ -0,0 +1,241 @@
+// keyspace.rs - Mixed-radix keyspaces
+// Every slot has its own radix, so index <-> digits conversion and the odometer
+// work exactly like the fixed-charset engines, just with a per-position base.
+
+/// Tokens a single slot can take, in enumeration order.
+#[derive(Clone, Debug)]
+pub enum Slot {
+    /// Explicit byte-string tokens, stored back to back.
+    Tokens { data: Vec<u8>, ends: Vec<usize> },
+    /// Decimal numbers `start..start + count`, zero padded to `width`.
+    Range { start: u64, count: u64, width: usize },
+}
+
+impl Slot {
+    pub fn from_tokens<I, T>(tokens: I) -> Slot
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut data = Vec::new();
+        let mut ends = Vec::new();
+        for t in tokens {
+            data.extend_from_slice(t.as_ref());
+            ends.push(data.len());
+        }
+        Slot::Tokens { data, ends }
+    }
+
+    /// One single-byte token per charset symbol.
+    pub fn from_charset(charset: &[u8]) -> Slot {
+        Slot::Tokens { data: charset.to_vec(), ends: (1..=charset.len()).collect() }
+    }
+
+    pub fn literal(bytes: &[u8]) -> Slot {
+        Slot::from_tokens([bytes])
+    }
+
+    pub fn radix(&self) -> u64 {
+        match self {
+            Slot::Tokens { ends, .. } => ends.len() as u64,
+            Slot::Range { count, .. } => *count,
+        }
+    }
+
+    /// Longest token this slot can render, in bytes.
+    pub fn max_len(&self) -> usize {
+        match self {
+            Slot::Tokens { ends, .. } => {
+                let mut prev = 0;
+                let mut max = 0;
+                for &e in ends {
+                    max = max.max(e - prev);
+                    prev = e;
+                }
+                max
+            }
+            Slot::Range { start, count, width } => {
+                let last = start + count.saturating_sub(1);
+                (*width).max(last.to_string().len())
+            }
+        }
+    }
+
+    #[inline(always)]
+    pub fn render(&self, digit: u64, out: &mut Vec<u8>) {
+        match self {
+            Slot::Tokens { data, ends } => {
+                let i = digit as usize;
+                let from = if i == 0 { 0 } else { ends[i - 1] };
+                out.extend_from_slice(&data[from..ends[i]]);
+            }
+            Slot::Range { start, width, .. } => push_decimal(start + digit, *width, out),
+        }
+    }
+}
+
+/// Writes `n` in decimal, left-padded with zeros to at least `width` digits.
+#[inline(always)]
+pub fn push_decimal(mut n: u64, width: usize, out: &mut Vec<u8>) {
+    let mut tmp = [0u8; 20];
+    let mut pos = tmp.len();
+    loop {
+        pos -= 1;
+        tmp[pos] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    for _ in (tmp.len() - pos)..width {
+        out.push(b'0');
+    }
+    out.extend_from_slice(&tmp[pos..]);
+}
+
+/// A product of slots enumerated like an odometer: the first slot is the most
+/// significant digit, the last slot changes fastest.
+#[derive(Clone, Debug)]
+pub struct Keyspace {
+    slots: Vec<Slot>,
+}
+
+impl Keyspace {
+    pub fn new(slots: Vec<Slot>) -> Keyspace {
+        Keyspace { slots }
+    }
+
+    /// The classic `charset ^ length` space.
+    pub fn charset(charset: &[u8], length: usize) -> Keyspace {
+        Keyspace::new(vec![Slot::from_charset(charset); length])
+    }
+
+    /// `self` followed by `other`: every combination of `self` with every
+    /// combination of `other`, the last slot of `other` changing fastest.
+    pub fn chain(mut self, other: Keyspace) -> Keyspace {
+        self.slots.extend(other.slots);
+        self
+    }
+
+    pub fn slots(&self) -> &[Slot] {
+        &self.slots
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Number of combinations, or `None` if it overflows u64.
+    pub fn total(&self) -> Option<u64> {
+        self.slots.iter().try_fold(1u64, |acc, s| acc.checked_mul(s.radix()))
+    }
+
+    /// Upper bound on the rendered size of one combination.
+    pub fn max_len(&self) -> usize {
+        self.slots.iter().map(Slot::max_len).sum()
+    }
+
+    #[inline(always)]
+    pub fn index_to_digits(&self, mut index: u64, digits: &mut [u64]) {
+        for pos in (0..digits.len()).rev() {
+            let radix = self.slots[pos].radix();
+            digits[pos] = index % radix;
+            index /= radix;
+        }
+    }
+
+    /// Advances to the next combination, returns true on wrap-around.
+    #[inline(always)]
+    pub fn increment(&self, digits: &mut [u64]) -> bool {
+        for pos in (0..digits.len()).rev() {
+            digits[pos] += 1;
+            if digits[pos] < self.slots[pos].radix() {
+                return false;
+            }
+            digits[pos] = 0;
+        }
+        true
+    }
+
+    /// Steps back to the previous combination, returns true on wrap-around
+    /// (the digits are then on the last combination).
+    #[inline(always)]
+    pub fn decrement(&self, digits: &mut [u64]) -> bool {
+        for pos in (0..digits.len()).rev() {
+            if digits[pos] > 0 {
+                digits[pos] -= 1;
+                return false;
+            }
+            digits[pos] = self.slots[pos].radix() - 1;
+        }
+        true
+    }
+
+    /// Sets `digits` to the last combination.
+    pub fn last_digits(&self, digits: &mut [u64]) {
+        for (d, slot) in digits.iter_mut().zip(&self.slots) {
+            *d = slot.radix() - 1;
+        }
+    }
+
+    /// Renders a combination, writing `separator` between slots.
+    #[inline(always)]
+    pub fn render(&self, digits: &[u64], separator: &[u8], out: &mut Vec<u8>) {
+        for (i, (slot, &d)) in self.slots.iter().zip(digits).enumerate() {
+            if i > 0 {
+                out.extend_from_slice(separator);
+            }
+            slot.render(d, out);
+        }
+    }
+}
+
+/// Keyspaces enumerated one after another, e.g. the variants of several words.
+/// Index `i` belongs to the space whose slice of the running total contains it.
+#[derive(Clone, Debug)]
+pub struct KeyspaceList {
+    spaces: Vec<Keyspace>,
+    starts: Vec<u64>,
+    total: u64,
+}
+
+impl KeyspaceList {
+    /// `None` if the combined total overflows u64.
+    pub fn new(spaces: Vec<Keyspace>) -> Option<KeyspaceList> {
+        let mut starts = Vec::with_capacity(spaces.len());
+        let mut total = 0u64;
+        for ks in &spaces {
+            starts.push(total);
+            total = total.checked_add(ks.total()?)?;
+        }
+        Some(KeyspaceList { spaces, starts, total })
+    }
+
+    pub fn spaces(&self) -> &[Keyspace] {
+        &self.spaces
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Upper bound on the rendered size of one combination.
+    pub fn max_len(&self) -> usize {
+        self.spaces.iter().map(Keyspace::max_len).max().unwrap_or(0)
+    }
+
+    /// Largest number of slots of any space.
+    pub fn max_slots(&self) -> usize {
+        self.spaces.iter().map(Keyspace::len).max().unwrap_or(0)
+    }
+
+    /// The space holding `index` and the index within that space.
+    pub fn locate(&self, index: u64) -> (usize, u64) {
+        let space = self.starts.partition_point(|&s| s <= index).saturating_sub(1);
+        (space, index - self.starts.get(space).copied().unwrap_or(0))
+    }
+}
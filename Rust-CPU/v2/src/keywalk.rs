@@ -0,0 +1,312 @@
+// keywalk.rs - Keyboard adjacency walks (qwer, 1qaz, zxcvbn, 7894...)
+//
+// Keys sit on rows at half-key x positions, so staggered keyboards and the
+// straight keypad grid share one neighbour rule: adjacent keys are at most one
+// row and two half-keys apart. A walk is a start key followed by a neighbour
+// of the previous key at every step. Walks are counted per start key and
+// length, which makes index <-> walk a bijection without gaps; with shift
+// variants every key whose shifted symbol differs (not the AZERTY ²) is
+// additionally plain or shifted, and the variants of a walk come one after
+// another. Counts then weigh every such key twice.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Qwerty,
+    Azerty,
+    Keypad,
+}
+
+impl Layout {
+    pub fn parse(name: &str) -> Result<Layout, String> {
+        match name {
+            "qwerty" => Ok(Layout::Qwerty),
+            "azerty" => Ok(Layout::Azerty),
+            "keypad" | "numpad" => Ok(Layout::Keypad),
+            _ => Err(format!("unknown layout '{}' (qwerty, azerty, keypad)", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Qwerty => "qwerty",
+            Layout::Azerty => "azerty",
+            Layout::Keypad => "keypad",
+        }
+    }
+
+    // (x of the first key in half-keys, plain keys, shifted keys); keys are 2 half-keys wide
+    fn rows(&self) -> &'static [(i32, &'static str, &'static str)] {
+        match self {
+            Layout::Qwerty => &[
+                (0, "`1234567890-=", "~!@#$%^&*()_+"),
+                (3, "qwertyuiop[]\\", "QWERTYUIOP{}|"),
+                (4, "asdfghjkl;'", "ASDFGHJKL:\""),
+                (5, "zxcvbnm,./", "ZXCVBNM<>?"),
+            ],
+            Layout::Azerty => &[
+                (0, "²&é\"'(-è_çà)=", "²1234567890°+"),
+                (3, "azertyuiop^$", "AZERTYUIOP¨£"),
+                (4, "qsdfghjklmù*", "QSDFGHJKLM%µ"),
+                (3, "<wxcvbn,;:!", ">WXCVBN?./§"),
+            ],
+            Layout::Keypad => &[
+                (0, "789", "789"),
+                (0, "456", "456"),
+                (0, "123", "123"),
+                // 0 is two keys wide, it touches 1, 2, 3 and .
+                (2, "0.", "0."),
+            ],
+        }
+    }
+
+    /// Whether shifted keys differ from plain ones.
+    pub fn has_shift(&self) -> bool {
+        *self != Layout::Keypad
+    }
+}
+
+struct Key {
+    plain: Vec<u8>,
+    shifted: Vec<u8>,
+    row: i32,
+    x: i32,
+}
+
+/// All walks of one length on one layout.
+pub struct KeyWalk {
+    keys: Vec<Key>,
+    neighbours: Vec<Vec<u16>>,
+    length: usize,
+    shift: bool,
+    /// `counts[(k - 1) * keys + key]`: walks of k keys starting at `key`, shift variants included.
+    counts: Vec<u64>,
+    walks: u64,
+}
+
+/// A position in the walk space, kept by each worker.
+#[derive(Clone, Debug, Default)]
+pub struct WalkState {
+    keys: Vec<u16>,
+    choices: Vec<u16>,
+    /// One bit per key with a distinct shifted symbol, the last such key lowest.
+    shift: u64,
+}
+
+impl KeyWalk {
+    /// Fails when the number of walks overflows u64.
+    pub fn new(layout: Layout, length: usize, shift: bool) -> Result<KeyWalk, String> {
+        if length == 0 {
+            return Err("walk length must be greater than 0".to_string());
+        }
+        if shift && length >= 64 {
+            return Err("shift variants need a walk length below 64".to_string());
+        }
+        let mut keys = Vec::new();
+        for (row, &(x0, plain, shifted)) in layout.rows().iter().enumerate() {
+            for (i, (p, s)) in plain.chars().zip(shifted.chars()).enumerate() {
+                let x = x0 + 2 * i as i32;
+                keys.push(Key { plain: p.to_string().into_bytes(), shifted: s.to_string().into_bytes(), row: row as i32, x });
+            }
+        }
+
+        // Neighbour order: right, down-right, down, down-left, left, up-left, up, up-right
+        let direction = |dr: i32, dx: i32| match (dr, dx.signum()) {
+            (0, 1) => 0,
+            (1, 1) => 1,
+            (1, 0) => 2,
+            (1, -1) => 3,
+            (0, -1) => 4,
+            (-1, -1) => 5,
+            (-1, 0) => 6,
+            _ => 7,
+        };
+        let neighbours: Vec<Vec<u16>> = keys
+            .iter()
+            .map(|k| {
+                let mut n: Vec<u16> = (0..keys.len() as u16)
+                    .filter(|&j| {
+                        let o = &keys[j as usize];
+                        let (dr, dx) = (o.row - k.row, o.x - k.x);
+                        (dr != 0 || dx != 0) && dr.abs() <= 1 && dx.abs() <= 2
+                    })
+                    .collect();
+                n.sort_by_key(|&j| direction(keys[j as usize].row - k.row, keys[j as usize].x - k.x));
+                n
+            })
+            .collect();
+
+        let n = keys.len();
+        let weight = |k: &Key| if shift && k.plain != k.shifted { 2 } else { 1 };
+        let mut counts: Vec<u64> = keys.iter().map(weight).collect();
+        for k in 1..length {
+            for (key, options) in keys.iter().zip(&neighbours) {
+                let mut sum = 0u64;
+                for &nb in options {
+                    sum = sum.checked_add(counts[(k - 1) * n + nb as usize]).ok_or("number of walks overflows u64")?;
+                }
+                counts.push(sum.checked_mul(weight(key)).ok_or("number of walks overflows u64")?);
+            }
+        }
+        let walks = counts[(length - 1) * n..].iter().try_fold(0u64, |acc, &c| acc.checked_add(c)).ok_or("number of walks overflows u64")?;
+        Ok(KeyWalk { keys, neighbours, length, shift, counts, walks })
+    }
+
+    #[inline]
+    fn shifts(&self, key: u16) -> bool {
+        let key = &self.keys[key as usize];
+        self.shift && key.plain != key.shifted
+    }
+
+    // Keys of the walk that have a shifted variant
+    #[inline]
+    fn shift_bits(&self, state: &WalkState) -> u32 {
+        state.keys.iter().filter(|&&k| self.shifts(k)).count() as u32
+    }
+
+    /// Number of walks including shift variants, or `None` on overflow.
+    pub fn total(&self) -> Option<u64> {
+        Some(self.walks)
+    }
+
+    /// Longest rendered walk, in bytes.
+    pub fn max_len(&self) -> usize {
+        self.length * self.keys.iter().map(|k| k.plain.len().max(k.shifted.len())).max().unwrap_or(1)
+    }
+
+    #[inline]
+    fn count(&self, keys_left: usize, key: u16) -> u64 {
+        self.counts[(keys_left - 1) * self.keys.len() + key as usize]
+    }
+
+    /// Moves `state` to the walk with the given index.
+    pub fn seek(&self, mut index: u64, state: &mut WalkState) {
+        state.keys.clear();
+        state.choices.clear();
+
+        let mut key = 0u16;
+        while (key as usize) < self.keys.len() - 1 && index >= self.count(self.length, key) {
+            index -= self.count(self.length, key);
+            key += 1;
+        }
+        state.keys.push(key);
+        state.choices.push(0);
+        // Every walk below a key repeats once per shift variant of the keys above it
+        let mut variants: u64 = if self.shifts(key) { 2 } else { 1 };
+        for step in 1..self.length {
+            let prev = *state.keys.last().unwrap();
+            let options = &self.neighbours[prev as usize];
+            let mut c = 0;
+            while c + 1 < options.len() && index >= variants * self.count(self.length - step, options[c]) {
+                index -= variants * self.count(self.length - step, options[c]);
+                c += 1;
+            }
+            state.keys.push(options[c]);
+            state.choices.push(c as u16);
+            if self.shifts(options[c]) {
+                variants *= 2;
+            }
+        }
+        state.shift = index;
+    }
+
+    /// Advances to the next walk, returns true on wrap-around.
+    #[inline]
+    pub fn next(&self, state: &mut WalkState) -> bool {
+        if self.shift {
+            state.shift += 1;
+            if state.shift < 1 << self.shift_bits(state) {
+                return false;
+            }
+            state.shift = 0;
+        }
+        let mut pos = self.length - 1;
+        loop {
+            if pos == 0 {
+                state.keys[0] += 1;
+                let wrapped = state.keys[0] as usize == self.keys.len();
+                if wrapped {
+                    state.keys[0] = 0;
+                }
+                self.reset_tail(state, 1);
+                return wrapped;
+            }
+            let options = &self.neighbours[state.keys[pos - 1] as usize];
+            let c = state.choices[pos] as usize + 1;
+            if c < options.len() {
+                state.choices[pos] = c as u16;
+                state.keys[pos] = options[c];
+                self.reset_tail(state, pos + 1);
+                return false;
+            }
+            pos -= 1;
+        }
+    }
+
+    // Every key has a neighbour, so the first choice is always valid
+    fn reset_tail(&self, state: &mut WalkState, from: usize) {
+        for pos in from..self.length {
+            state.choices[pos] = 0;
+            state.keys[pos] = self.neighbours[state.keys[pos - 1] as usize][0];
+        }
+    }
+
+    /// Appends the walk, with `separator` between keys.
+    #[inline]
+    pub fn render(&self, state: &WalkState, separator: &[u8], out: &mut Vec<u8>) {
+        let mut bit = if self.shift { self.shift_bits(state) } else { 0 };
+        for (pos, &k) in state.keys.iter().enumerate() {
+            if pos > 0 {
+                out.extend_from_slice(separator);
+            }
+            let key = &self.keys[k as usize];
+            let shifted = self.shifts(k) && {
+                bit -= 1;
+                state.shift >> bit & 1 == 1
+            };
+            out.extend_from_slice(if shifted { &key.shifted } else { &key.plain });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check_seek(walk: &KeyWalk) {
+        let total = walk.total().unwrap();
+        let mut state = WalkState::default();
+        walk.seek(0, &mut state);
+        for i in 0..total {
+            let mut sought = WalkState::default();
+            walk.seek(i, &mut sought);
+            assert_eq!((&sought.keys, &sought.choices, sought.shift), (&state.keys, &state.choices, state.shift), "index {}", i);
+            assert_eq!(walk.next(&mut state), i == total - 1);
+        }
+    }
+
+    #[test]
+    fn seek_matches_stepping() {
+        check_seek(&KeyWalk::new(Layout::Keypad, 3, false).unwrap());
+    }
+
+    #[test]
+    fn seek_matches_stepping_with_shift() {
+        check_seek(&KeyWalk::new(Layout::Qwerty, 2, true).unwrap());
+    }
+
+    #[test]
+    fn shift_variants_are_distinct() {
+        // ² has no shifted symbol of its own, walks through it must not repeat
+        let walk = KeyWalk::new(Layout::Azerty, 3, true).unwrap();
+        check_seek(&walk);
+        let mut state = WalkState::default();
+        walk.seek(0, &mut state);
+        let mut seen = std::collections::HashSet::new();
+        for _ in 0..walk.total().unwrap() {
+            let mut out = Vec::new();
+            walk.render(&state, b"", &mut out);
+            assert!(seen.insert(out.clone()), "duplicate walk {}", String::from_utf8_lossy(&out));
+            walk.next(&mut state);
+        }
+    }
+}
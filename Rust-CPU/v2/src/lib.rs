@@ -0,0 +1,23 @@
+// ComboGen library - building blocks shared by the generator engines
+
+pub mod binfmt;
+pub mod codec;
+pub mod dates;
+pub mod encoding;
+pub mod exclude;
+pub mod exec;
+pub mod freq;
+pub mod hash;
+pub mod keyspace;
+pub mod keywalk;
+pub mod markov;
+pub mod parts;
+pub mod pattern;
+pub mod record;
+pub mod rules;
+pub mod schedule;
+pub mod search;
+pub mod stdio;
+pub mod variants;
+pub mod wordlist;
+pub mod writer;
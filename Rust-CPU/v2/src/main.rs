@@ -2,16 +2,39 @@
 // Automatically chooses Ultra-Fast version for performance
 // Use --version optimized to select optimized version
 // Use --version fixed to select fixed version
+// `combo_gen merge <manifest> <output>` joins split or per-thread part files
+// `combo_gen product <list>... [options]` enumerates every concatenation of one line per list
+// `combo_gen train <corpus> [model]` builds the model used by `--order markov --stats`
 
 mod combo_gen_ultra;
 mod combo_gen_optimized;
 mod combo_gen_fixed;
 
 use std::env;
+use combo_gen::status;
+use combo_gen::stdio;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
+    if args.get(1).map(String::as_str) == Some("merge") {
+        merge(&args);
+        return;
+    }
+    if args.get(1).map(String::as_str) == Some("train") {
+        train(&args);
+        return;
+    }
+
+    // Keep stdout clean when the combinations themselves are streamed there,
+    // or when it is shared with an --exec consumer
+    stdio::set_status_to_stderr(args.windows(2).any(|w| w[0] == "--output" && w[1] == "-") || args.iter().any(|a| a == "--exec"));
+
+    if args.get(1).map(String::as_str) == Some("product") {
+        product(&args);
+        return;
+    }
+
     // Check version selection
     let version = if args.contains(&"--version".to_string()) {
         if let Some(pos) = args.iter().position(|x| x == "--version") {
@@ -29,16 +52,68 @@ fn main() {
 
     match version {
         "optimized" => {
-            println!("⚡ Running Optimized version...");
+            status!("⚡ Running Optimized version...");
             combo_gen_optimized::main();
         }
         "fixed" => {
-            println!("🔧 Running Fixed version...");
+            status!("🔧 Running Fixed version...");
             combo_gen_fixed::main();
         }
         _ => {
-            println!("🚀 Running Ultra-Fast version (default - best performance)...");
+            status!("🚀 Running Ultra-Fast version (default - best performance)...");
             combo_gen_ultra::main();
         }
     }
 }
+
+// Lists come first, the remaining options go to the ultra engine as they are
+fn product(args: &[String]) {
+    let lists: Vec<&String> = args[2..].iter().take_while(|a| !a.starts_with("--")).collect();
+    if lists.is_empty() {
+        eprintln!("Usage: {} product <list1> <list2> ... [--separator str] [options]", args[0]);
+        std::process::exit(1);
+    }
+    let mut engine_args = vec![args[0].clone()];
+    for list in &lists {
+        engine_args.push("--list".to_string());
+        engine_args.push(list.to_string());
+    }
+    engine_args.extend(args[2 + lists.len()..].iter().cloned());
+    combo_gen_ultra::run(engine_args);
+}
+
+fn merge(args: &[String]) {
+    if args.len() != 4 {
+        eprintln!("Usage: {} merge <manifest> <output>", args[0]);
+        std::process::exit(1);
+    }
+    let output = std::fs::File::create(&args[3]).expect("Failed to create output file");
+    match combo_gen::parts::merge(&args[2], output) {
+        Ok(((start, end), bytes)) => {
+            status!("Merged indices {}..{} ({} bytes) into {}", start, end, bytes, args[3]);
+        }
+        Err(e) => {
+            eprintln!("Error: merge failed: {}", e);
+            let _ = std::fs::remove_file(&args[3]);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn train(args: &[String]) {
+    if args.len() < 3 || args.len() > 4 {
+        eprintln!("Usage: {} train <corpus.txt|-> [model.bin]", args[0]);
+        std::process::exit(1);
+    }
+    let model_path = args.get(3).map_or("model.bin", String::as_str);
+    let corpus = combo_gen::wordlist::read(&args[2]).unwrap_or_else(|e| {
+        eprintln!("Error: {}: {}", args[2], e);
+        std::process::exit(1);
+    });
+    let model = combo_gen::markov::Model::train(&corpus);
+    if let Err(e) = model.save(model_path) {
+        eprintln!("Error: failed to write {}: {}", model_path, e);
+        std::process::exit(1);
+    }
+    status!("Trained {} positions from {} into {}", model.positions(), args[2], model_path);
+}
@@ -0,0 +1,252 @@
+// markov.rs - Per-position character transition model and likelihood ordering
+//
+// `Model` counts, for every position, how often each byte follows each other
+// byte (or starts a word). `MarkovOrder` turns the model into a bijection of
+// the `charset ^ length` index space: digits are ranks (0 = most likely next
+// symbol after the previous one), and the ranks are enumerated by increasing
+// rank sum, so likely candidates come first and every candidate still comes
+// exactly once.
+//
+// Model file (little endian): magic "CGMK", version u16, positions u16,
+// entry count u64, then entries of position u8, previous byte u16 (256 = start
+// of word), byte u8, count u32.
+
+use std::fs;
+use std::io;
+
+use crate::wordlist;
+
+const MAGIC: &[u8; 4] = b"CGMK";
+const VERSION: u16 = 1;
+/// Positions with their own statistics; later positions reuse the last one.
+pub const MAX_POSITIONS: usize = 32;
+const START: usize = 256;
+const ROW: usize = 257 * 256;
+
+pub struct Model {
+    /// `counts[pos][prev * 256 + byte]`, `prev` 256 meaning start of word.
+    counts: Vec<Vec<u32>>,
+}
+
+impl Model {
+    pub fn train(corpus: &[u8]) -> Model {
+        let mut counts: Vec<Vec<u32>> = Vec::new();
+        for word in wordlist::lines(corpus) {
+            let mut prev = START;
+            for (pos, &b) in word.iter().take(MAX_POSITIONS).enumerate() {
+                if counts.len() <= pos {
+                    counts.push(vec![0; ROW]);
+                }
+                let c = &mut counts[pos][prev * 256 + b as usize];
+                *c = c.saturating_add(1);
+                prev = b as usize;
+            }
+        }
+        Model { counts }
+    }
+
+    pub fn positions(&self) -> usize {
+        self.counts.len()
+    }
+
+    #[inline]
+    fn count(&self, pos: usize, prev: usize, byte: u8) -> u32 {
+        match self.counts.get(pos.min(self.counts.len().saturating_sub(1))) {
+            Some(row) => row[prev * 256 + byte as usize],
+            None => 0,
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for (pos, row) in self.counts.iter().enumerate() {
+            for (i, &n) in row.iter().enumerate().filter(|(_, &n)| n > 0) {
+                entries.push(pos as u8);
+                entries.extend_from_slice(&((i / 256) as u16).to_le_bytes());
+                entries.push((i % 256) as u8);
+                entries.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+        let mut out = Vec::with_capacity(16 + entries.len());
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        out.extend_from_slice(&(self.counts.len() as u16).to_le_bytes());
+        out.extend_from_slice(&((entries.len() / 8) as u64).to_le_bytes());
+        out.extend_from_slice(&entries);
+        fs::write(path, out)
+    }
+
+    pub fn load(path: &str) -> io::Result<Model> {
+        let data = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        if data.len() < 16 || &data[0..4] != MAGIC {
+            return Err(invalid("not a combo_gen model file"));
+        }
+        if u16::from_le_bytes([data[4], data[5]]) != VERSION {
+            return Err(invalid("unsupported model version"));
+        }
+        let positions = u16::from_le_bytes([data[6], data[7]]) as usize;
+        let entries = u64::from_le_bytes(data[8..16].try_into().unwrap()) as usize;
+        if positions > MAX_POSITIONS || data.len() != 16 + entries * 8 {
+            return Err(invalid("truncated or corrupt model file"));
+        }
+        let mut counts = vec![vec![0u32; ROW]; positions];
+        for e in data[16..].chunks_exact(8) {
+            let (pos, prev, byte) = (e[0] as usize, u16::from_le_bytes([e[1], e[2]]) as usize, e[3] as usize);
+            if pos >= positions || prev > START {
+                return Err(invalid("corrupt model entry"));
+            }
+            counts[pos][prev * 256 + byte] = u32::from_le_bytes(e[4..8].try_into().unwrap());
+        }
+        Ok(Model { counts })
+    }
+}
+
+/// Likelihood-first enumeration of `charset ^ length` under a `Model`.
+pub struct MarkovOrder {
+    length: usize,
+    base: usize,
+    /// `symbols[(pos * (base + 1) + prev) * base + rank]` = charset index,
+    /// `prev` being a charset index or `base` at the start of the word.
+    symbols: Vec<u16>,
+    /// `ways[k * (max_sum + 1) + s]`: rank vectors of length k with sum s.
+    ways: Vec<u64>,
+    max_sum: usize,
+}
+
+impl MarkovOrder {
+    /// Fails when the charset is too large or the keyspace overflows u64.
+    pub fn new(model: &Model, charset: &[u8], length: usize) -> Result<MarkovOrder, String> {
+        let base = charset.len();
+        if base == 0 || base > u16::MAX as usize {
+            return Err("charset size must be between 1 and 65535".to_string());
+        }
+        let mut symbols = Vec::with_capacity(length * (base + 1) * base);
+        for pos in 0..length {
+            for prev in 0..=base {
+                let prev_byte = if prev == base { START } else { charset[prev] as usize };
+                let mut ranked: Vec<u16> = (0..base as u16).collect();
+                // Stable sort keeps the charset order between equally likely symbols
+                ranked.sort_by_key(|&i| std::cmp::Reverse(model.count(pos, prev_byte, charset[i as usize])));
+                symbols.extend_from_slice(&ranked);
+            }
+        }
+
+        let max_sum = length * (base - 1);
+        let mut ways = vec![0u64; (length + 1) * (max_sum + 1)];
+        ways[0] = 1;
+        for k in 1..=length {
+            for s in 0..=max_sum {
+                let mut n = 0u64;
+                for d in 0..base.min(s + 1) {
+                    n = n.checked_add(ways[(k - 1) * (max_sum + 1) + s - d]).ok_or("keyspace overflows u64")?;
+                }
+                ways[k * (max_sum + 1) + s] = n;
+            }
+        }
+        Ok(MarkovOrder { length, base, symbols, ways, max_sum })
+    }
+
+    #[inline]
+    fn ways(&self, k: usize, s: usize) -> u64 {
+        self.ways[k * (self.max_sum + 1) + s]
+    }
+
+    /// Rank vector of the `index`-th candidate.
+    pub fn index_to_ranks(&self, mut index: u64, ranks: &mut [u32]) {
+        let mut sum = 0;
+        while sum < self.max_sum && index >= self.ways(self.length, sum) {
+            index -= self.ways(self.length, sum);
+            sum += 1;
+        }
+        for (pos, rank) in ranks.iter_mut().enumerate() {
+            let rest = self.length - pos - 1;
+            let mut d = 0;
+            while d + 1 < self.base && d < sum && index >= self.ways(rest, sum - d) {
+                index -= self.ways(rest, sum - d);
+                d += 1;
+            }
+            *rank = d as u32;
+            sum -= d;
+        }
+    }
+
+    /// Advances to the next rank vector: the next one with the same rank sum
+    /// in lexicographic order, else the first one of the next sum. Returns
+    /// true after the last candidate.
+    #[inline]
+    pub fn next(&self, ranks: &mut [u32]) -> bool {
+        let top = (self.base - 1) as u32;
+        let mut suffix = 0u32;
+        for i in (0..ranks.len()).rev() {
+            if suffix > 0 && ranks[i] < top {
+                ranks[i] += 1;
+                fill_smallest(&mut ranks[i + 1..], suffix - 1, top);
+                return false;
+            }
+            suffix += ranks[i];
+        }
+        if suffix as usize >= self.max_sum {
+            fill_smallest(ranks, 0, top);
+            return true;
+        }
+        fill_smallest(ranks, suffix + 1, top);
+        false
+    }
+
+    /// Appends the candidate for `ranks`, with `separator` between symbols.
+    #[inline]
+    pub fn render(&self, ranks: &[u32], charset: &[u8], separator: &[u8], out: &mut Vec<u8>) {
+        let mut prev = self.base;
+        for (pos, &r) in ranks.iter().enumerate() {
+            if pos > 0 {
+                out.extend_from_slice(separator);
+            }
+            let sym = self.symbols[(pos * (self.base + 1) + prev) * self.base + r as usize] as usize;
+            out.push(charset[sym]);
+            prev = sym;
+        }
+    }
+}
+
+// Lexicographically smallest vector with the given sum: everything at the end
+fn fill_smallest(ranks: &mut [u32], mut sum: u32, top: u32) {
+    for r in ranks.iter_mut().rev() {
+        *r = sum.min(top);
+        sum -= *r;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_matches_stepping() {
+        let model = Model::train(b"abc\nbca\ncab\naab\n");
+        let order = MarkovOrder::new(&model, b"abcd", 3).unwrap();
+        let mut ranks = [0u32; 3];
+        order.index_to_ranks(0, &mut ranks);
+        let mut seen = std::collections::HashSet::new();
+        for i in 0..64u64 {
+            let mut sought = [0u32; 3];
+            order.index_to_ranks(i, &mut sought);
+            assert_eq!(sought, ranks, "index {}", i);
+            assert!(seen.insert(ranks));
+            assert_eq!(order.next(&mut ranks), i == 63);
+        }
+        // After the last candidate the order starts over
+        assert_eq!(ranks, [0, 0, 0]);
+    }
+
+    #[test]
+    fn likely_candidates_come_first() {
+        let model = Model::train(b"abc\nabc\nabd\n");
+        let order = MarkovOrder::new(&model, b"dcba", 3).unwrap();
+        let mut ranks = [0u32; 3];
+        order.index_to_ranks(0, &mut ranks);
+        let mut out = Vec::new();
+        order.render(&ranks, b"dcba", b"", &mut out);
+        assert_eq!(out, b"abc");
+    }
+}
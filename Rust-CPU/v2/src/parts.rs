@@ -0,0 +1,206 @@
+// parts.rs - Multi-file output: part naming, manifests, merging and size parsing
+//
+// A manifest is a small TSV file next to the parts:
+//   # combo_gen manifest v1
+//   # range 0     3000
+//   # part  start  end  bytes  crc32  file
+//   0       0      1000 5000   1a2b3c4d combos.000.txt
+// `start..end` is the keyspace index range of the part (end exclusive), `bytes`
+// and `crc32` describe the file as stored on disk (after compression). The
+// range line holds the indices of the whole run, so missing parts at either
+// end are detected as well.
+
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+const MANIFEST_HEADER: &str = "# combo_gen manifest v1";
+const RANGE_PREFIX: &str = "# range\t";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartInfo {
+    pub part: u64,
+    pub start: u64,
+    pub end: u64,
+    pub bytes: u64,
+    pub crc32: u32,
+    /// File name, relative to the manifest's directory.
+    pub file: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Manifest {
+    /// Index range the parts should cover together; absent in older manifests.
+    pub range: Option<(u64, u64)>,
+    pub parts: Vec<PartInfo>,
+}
+
+impl Manifest {
+    pub fn read<P: AsRef<Path>>(path: P) -> io::Result<Manifest> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(MANIFEST_HEADER) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a combo_gen manifest"));
+        }
+        let mut range = None;
+        let mut parts = Vec::new();
+        for line in lines.filter(|l| !l.trim().is_empty()) {
+            let bad = || io::Error::new(io::ErrorKind::InvalidData, format!("bad manifest line: {}", line));
+            if let Some(rest) = line.strip_prefix(RANGE_PREFIX) {
+                let (start, end) = rest.split_once('\t').ok_or_else(bad)?;
+                range = Some((start.parse().map_err(|_| bad())?, end.parse().map_err(|_| bad())?));
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let cols: Vec<&str> = line.split('\t').collect();
+            if cols.len() != 6 {
+                return Err(bad());
+            }
+            parts.push(PartInfo {
+                part: cols[0].parse().map_err(|_| bad())?,
+                start: cols[1].parse().map_err(|_| bad())?,
+                end: cols[2].parse().map_err(|_| bad())?,
+                bytes: cols[3].parse().map_err(|_| bad())?,
+                crc32: u32::from_str_radix(cols[4], 16).map_err(|_| bad())?,
+                file: cols[5].to_string(),
+            });
+        }
+        Ok(Manifest { range, parts })
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut text = format!("{}\n", MANIFEST_HEADER);
+        if let Some((start, end)) = self.range {
+            text.push_str(&format!("{}{}\t{}\n", RANGE_PREFIX, start, end));
+        }
+        text.push_str("# part\tstart\tend\tbytes\tcrc32\tfile\n");
+        for p in &self.parts {
+            text.push_str(&format!("{}\t{}\t{}\t{}\t{:08x}\t{}\n", p.part, p.start, p.end, p.bytes, p.crc32, p.file));
+        }
+        fs::write(path, text)
+    }
+
+    /// Adds or replaces a part (matched by part number), keeping parts sorted by index.
+    pub fn insert(&mut self, info: PartInfo) {
+        self.parts.retain(|p| p.part != info.part);
+        self.parts.push(info);
+        self.parts.sort_by_key(|p| p.start);
+    }
+
+    /// Index range covered by the parts, or an error naming the first gap or overlap.
+    pub fn covered_range(&self) -> Result<(u64, u64), String> {
+        let first = self.parts.first().ok_or("manifest lists no parts")?;
+        let mut end = first.start;
+        for p in &self.parts {
+            if p.start != end {
+                return Err(format!("part {} starts at index {} but the previous part ends at {}", p.part, p.start, end));
+            }
+            end = p.end;
+        }
+        Ok((first.start, end))
+    }
+}
+
+/// Concatenates the parts listed in a manifest into `output` in index order,
+/// checking that they cover the run's whole range and match their size and CRC32.
+/// Returns the merged index range and the number of bytes written.
+pub fn merge<P: AsRef<Path>, W: Write>(manifest_path: P, output: W) -> io::Result<((u64, u64), u64)> {
+    let manifest_path = manifest_path.as_ref();
+    let manifest = Manifest::read(manifest_path)?;
+    let range = manifest.covered_range().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    match manifest.range {
+        Some(expected) if expected != range => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("parts cover indices {}..{} but the run covers {}..{}", range.0, range.1, expected.0, expected.1),
+            ))
+        }
+        _ => {}
+    }
+    let dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+    let mut out = BufWriter::with_capacity(4 * 1024 * 1024, output);
+    let mut buf = vec![0u8; 1024 * 1024];
+    let mut total = 0u64;
+    for p in &manifest.parts {
+        let mut file = File::open(dir.join(&p.file))?;
+        let mut crc = crc32fast::Hasher::new();
+        let mut bytes = 0u64;
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            crc.update(&buf[..n]);
+            out.write_all(&buf[..n])?;
+            bytes += n as u64;
+        }
+        if bytes != p.bytes || crc.finalize() != p.crc32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} does not match its manifest entry (size or checksum)", p.file)));
+        }
+        total += bytes;
+    }
+    out.flush()?;
+    Ok((range, total))
+}
+
+/// `combos.txt` + part 7 -> `combos.007.txt`; the number goes before the first extension.
+pub fn part_path(output: &str, part: u64, width: usize) -> String {
+    let (dir, name) = match output.rfind(['/', '\\']) {
+        Some(i) => output.split_at(i + 1),
+        None => ("", output),
+    };
+    match name.find('.') {
+        Some(dot) if dot > 0 => format!("{}{}.{:0w$}{}", dir, &name[..dot], part, &name[dot..], w = width),
+        _ => format!("{}{}.{:0w$}", dir, name, part, w = width),
+    }
+}
+
+/// `combos.txt.gz` -> `combos.manifest`
+pub fn manifest_path(output: &str) -> String {
+    let (dir, name) = match output.rfind(['/', '\\']) {
+        Some(i) => output.split_at(i + 1),
+        None => ("", output),
+    };
+    let stem = match name.find('.') {
+        Some(dot) if dot > 0 => &name[..dot],
+        _ => name,
+    };
+    format!("{}{}.manifest", dir, stem)
+}
+
+/// Digits used for part numbers, at least three.
+pub fn part_width(parts: u64) -> usize {
+    parts.saturating_sub(1).to_string().len().max(3)
+}
+
+/// Parses sizes like `4096`, `64K`, `1.5G` (binary units).
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let (num, mult) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let mult: u64 = match c.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                'T' => 1 << 40,
+                'B' => 1,
+                _ => return Err(format!("unknown size suffix in '{}'", s)),
+            };
+            (&s[..i], mult)
+        }
+        _ => (s, 1),
+    };
+    let value: f64 = num.trim().parse().map_err(|_| format!("invalid size '{}'", s))?;
+    if value <= 0.0 {
+        return Err(format!("size must be positive: '{}'", s));
+    }
+    Ok((value * mult as f64) as u64)
+}
+
+/// File name of `path` relative to its directory, as stored in manifests.
+pub fn file_name(path: &str) -> String {
+    Path::new(path).file_name().map_or_else(|| path.to_string(), |n| n.to_string_lossy().into_owned())
+}
@@ -0,0 +1,245 @@
+// pattern.rs - Template DSL compiled into a mixed-radix keyspace
+//
+//   ACME-{0000-9999}-[A-F]{2}-{red|green|blue}
+//
+//   literal      any other character, `\` escapes the next one
+//   [A-Fx-z_]    character class with ranges
+//   ?l ?u ?d ?h ?H ?s ?a   built-in classes (hashcat style), `??` is a literal `?`
+//   {0000-9999}  numeric range, zero padded when the start has leading zeros
+//   {red|blue}   alternation
+//   X{n}         repeat the previous element n times
+
+use crate::keyspace::{Keyspace, Slot};
+
+const SPECIALS: &str = " !\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+
+/// Parses a template into a keyspace. Adjacent fixed parts are merged into a
+/// single literal slot.
+pub fn parse(template: &str) -> Result<Keyspace, String> {
+    let chars: Vec<char> = template.chars().collect();
+    let mut atoms: Vec<Slot> = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let c = *chars.get(i + 1).ok_or("dangling '\\' at end of pattern")?;
+                atoms.push(Slot::literal(c.to_string().as_bytes()));
+                i += 2;
+            }
+            '[' => {
+                let (set, next) = parse_class(&chars, i + 1)?;
+                atoms.push(char_slot(&set));
+                i = next;
+            }
+            '?' => {
+                let c = *chars.get(i + 1).ok_or("dangling '?' at end of pattern")?;
+                if c == '?' {
+                    atoms.push(Slot::literal(b"?"));
+                } else {
+                    atoms.push(char_slot(&builtin_class(c)?));
+                }
+                i += 2;
+            }
+            '{' => {
+                let close = find_unescaped(&chars, i + 1, '}')
+                    .ok_or_else(|| format!("unclosed '{{' at position {}", i))?;
+                let body: String = chars[i + 1..close].iter().collect();
+                i = close + 1;
+
+                if !atoms.is_empty() && is_number(&body) {
+                    let n: usize = body.parse().map_err(|_| format!("bad repeat count {{{}}}", body))?;
+                    if n == 0 {
+                        return Err("repeat count must be at least 1".to_string());
+                    }
+                    let last = atoms.last().unwrap().clone();
+                    for _ in 1..n {
+                        atoms.push(last.clone());
+                    }
+                } else if let Some(slot) = parse_range(&body)? {
+                    atoms.push(slot);
+                } else {
+                    atoms.push(Slot::from_tokens(split_alternatives(&body)));
+                }
+            }
+            c => {
+                atoms.push(Slot::literal(c.to_string().as_bytes()));
+                i += 1;
+            }
+        }
+    }
+
+    if atoms.is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+
+    // Fold runs of single-token slots into one literal.
+    let mut slots: Vec<Slot> = Vec::with_capacity(atoms.len());
+    let mut literal: Vec<u8> = Vec::new();
+    for atom in atoms {
+        if atom.radix() == 1 {
+            atom.render(0, &mut literal);
+        } else {
+            if !literal.is_empty() {
+                slots.push(Slot::literal(&literal));
+                literal.clear();
+            }
+            slots.push(atom);
+        }
+    }
+    if !literal.is_empty() {
+        slots.push(Slot::literal(&literal));
+    }
+
+    Ok(Keyspace::new(slots))
+}
+
+fn parse_class(chars: &[char], mut i: usize) -> Result<(Vec<char>, usize), String> {
+    let mut set: Vec<char> = Vec::new();
+    loop {
+        let c = match chars.get(i) {
+            Some(']') => break,
+            Some('\\') => {
+                i += 1;
+                *chars.get(i).ok_or("dangling '\\' in character class")?
+            }
+            Some(&c) => c,
+            None => return Err("unclosed '[' in pattern".to_string()),
+        };
+        i += 1;
+
+        if chars.get(i) == Some(&'-') && chars.get(i + 1).is_some_and(|&n| n != ']') {
+            let mut end = chars[i + 1];
+            i += 2;
+            if end == '\\' {
+                end = *chars.get(i).ok_or("dangling '\\' in character class")?;
+                i += 1;
+            }
+            if end < c {
+                return Err(format!("invalid class range {}-{}", c, end));
+            }
+            for r in c..=end {
+                if !set.contains(&r) {
+                    set.push(r);
+                }
+            }
+        } else if !set.contains(&c) {
+            set.push(c);
+        }
+    }
+    if set.is_empty() {
+        return Err("empty character class".to_string());
+    }
+    Ok((set, i + 1))
+}
+
+fn builtin_class(c: char) -> Result<Vec<char>, String> {
+    Ok(match c {
+        'l' => ('a'..='z').collect(),
+        'u' => ('A'..='Z').collect(),
+        'd' => ('0'..='9').collect(),
+        'h' => ('0'..='9').chain('a'..='f').collect(),
+        'H' => ('0'..='9').chain('A'..='F').collect(),
+        's' => SPECIALS.chars().collect(),
+        'a' => ('a'..='z').chain('A'..='Z').chain('0'..='9').chain(SPECIALS.chars()).collect(),
+        _ => return Err(format!("unknown class ?{}", c)),
+    })
+}
+
+fn char_slot(set: &[char]) -> Slot {
+    Slot::from_tokens(set.iter().map(|c| c.to_string().into_bytes()))
+}
+
+fn find_unescaped(chars: &[char], mut i: usize, target: char) -> Option<usize> {
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i] == target {
+            return Some(i);
+        }
+        i += 1;
+    }
+    None
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn parse_range(body: &str) -> Result<Option<Slot>, String> {
+    let (from, to) = match body.split_once('-') {
+        Some((a, b)) if is_number(a) && is_number(b) => (a, b),
+        _ => return Ok(None),
+    };
+    let start: u64 = from.parse().map_err(|_| format!("range start too large: {}", from))?;
+    let end: u64 = to.parse().map_err(|_| format!("range end too large: {}", to))?;
+    if end < start {
+        return Err(format!("invalid range {{{}}}: end before start", body));
+    }
+    let width = if from.len() > 1 && from.starts_with('0') { from.len() } else { 0 };
+    let count = (end - start).checked_add(1).ok_or("range too large")?;
+    Ok(Some(Slot::Range { start, count, width }))
+}
+
+fn split_alternatives(body: &str) -> Vec<Vec<u8>> {
+    let mut options = Vec::new();
+    let mut current = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(n) = chars.next() {
+                    current.push(n);
+                }
+            }
+            '|' => options.push(std::mem::take(&mut current).into_bytes()),
+            _ => current.push(c),
+        }
+    }
+    options.push(current.into_bytes());
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(template: &str) -> Vec<String> {
+        let ks = parse(template).unwrap();
+        let mut digits = vec![0; ks.len()];
+        (0..ks.total().unwrap())
+            .map(|i| {
+                let mut out = Vec::new();
+                ks.index_to_digits(i, &mut digits);
+                ks.render(&digits, b"", &mut out);
+                String::from_utf8(out).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn classes_and_ranges_enumerate_in_order() {
+        assert_eq!(expand("A{0-2}[xy]"), ["A0x", "A0y", "A1x", "A1y", "A2x", "A2y"]);
+        assert_eq!(expand("{08-10}"), ["08", "09", "10"]);
+        assert_eq!(expand("[a-c_]"), ["a", "b", "c", "_"]);
+        assert_eq!(parse("?d?l?u?s?a?h?H").unwrap().total(), Some(10 * 26 * 26 * 33 * 95 * 16 * 16));
+    }
+
+    #[test]
+    fn alternation_repeat_and_escapes() {
+        assert_eq!(expand("{red|gr\\|n}-"), ["red-", "gr|n-"]);
+        assert_eq!(expand("[01]{3}").len(), 8);
+        assert_eq!(expand("a\\[b??\\{"), ["a[b?{"]);
+        // Fixed parts fold into one literal slot
+        assert_eq!(parse("ab-?dcd").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn invalid_patterns_are_rejected() {
+        for bad in ["", "[b-a]", "[]", "[ab", "{0-9", "?q", "x\\", "x{0}", "{9-1}"] {
+            assert!(parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+    }
+}
@@ -0,0 +1,244 @@
+// record.rs - Turns a generated candidate into one output record
+// candidate -> hash, encoding -> format (index, hash, value, length, mask) -> delimiter
+
+use crate::encoding::{self, Encoding};
+use crate::hash::{HashOutput, MAX_DIGEST};
+use crate::keyspace::push_decimal;
+
+/// Output record layout selected with `--format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Jsonl,
+    Csv,
+    Tsv,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, String> {
+        match name {
+            "text" | "txt" => Ok(Format::Text),
+            "jsonl" | "json" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(format!("unknown format '{}' (text, jsonl, csv, tsv)", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Jsonl => "jsonl",
+            Format::Csv => "csv",
+            Format::Tsv => "tsv",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RecordLayout {
+    pub encoding: Encoding,
+    pub format: Format,
+    /// Adds the candidate length (in bytes, before encoding) as a column.
+    pub with_length: bool,
+    /// Adds the position of the candidate's mask in a list of keyspaces as a column.
+    pub with_mask: bool,
+    pub delimiter: Vec<u8>,
+    /// Adds the hex digest of the candidate (`hash:value` in text format).
+    pub hash: Option<HashOutput>,
+}
+
+impl Default for RecordLayout {
+    fn default() -> Self {
+        RecordLayout { encoding: Encoding::Raw, format: Format::Text, with_length: false, with_mask: false, delimiter: b"\n".to_vec(), hash: None }
+    }
+}
+
+impl RecordLayout {
+    /// Plain records are the candidate bytes followed by the delimiter, so
+    /// engines can render straight into their output buffer.
+    pub fn is_plain(&self) -> bool {
+        self.encoding == Encoding::Raw && self.format == Format::Text && self.hash.is_none()
+    }
+
+    /// Column header for CSV and TSV output, including the delimiter.
+    pub fn header(&self) -> Option<Vec<u8>> {
+        let sep = match self.format {
+            Format::Csv => ",",
+            Format::Tsv => "\t",
+            _ => return None,
+        };
+        let mut h = match self.hash {
+            Some(ref hash) if hash.hashes_only => format!("index{}hash", sep),
+            Some(_) => format!("index{}hash{}value", sep, sep),
+            None => format!("index{}value", sep),
+        };
+        if self.with_length {
+            h.push_str(sep);
+            h.push_str("length");
+        }
+        if self.with_mask {
+            h.push_str(sep);
+            h.push_str("mask");
+        }
+        let mut h = h.into_bytes();
+        h.extend_from_slice(&self.delimiter);
+        Some(h)
+    }
+
+    /// Upper bound on the size of one record.
+    pub fn max_len(&self, candidate_len: usize) -> usize {
+        let value = self.encoding.max_len(candidate_len) + self.hash.as_ref().map_or(0, |h| h.algo.output_len() * 2 + 8);
+        let extra = match self.format {
+            Format::Text => 0,
+            // {"i":<20>,"v":"<escaped>","len":<20>,"m":<20>}, \u00XX escapes make a byte 6 long
+            Format::Jsonl => 57 + value * 5,
+            // Quoting or escaping at most doubles the value
+            Format::Csv | Format::Tsv => 65 + value,
+        };
+        value + extra + self.delimiter.len()
+    }
+
+    /// Appends the record for `candidate` at keyspace position `index`, generated
+    /// from mask number `mask`, to `out`. `scratch` holds the encoded value between steps.
+    #[inline]
+    pub fn write(&self, index: u64, mask: usize, candidate: &[u8], scratch: &mut Vec<u8>, out: &mut Vec<u8>) {
+        let value: &[u8] = if self.encoding == Encoding::Raw {
+            candidate
+        } else {
+            scratch.clear();
+            self.encoding.encode(candidate, scratch);
+            scratch
+        };
+
+        let mut hex = [0u8; MAX_DIGEST * 2];
+        let hex: &[u8] = match self.hash {
+            Some(ref hash) => {
+                let mut digest = [0u8; MAX_DIGEST];
+                let n = hash.algo.output_len();
+                hash.algo.digest(candidate, &mut digest);
+                encoding::hex_into(&digest[..n], &mut hex);
+                &hex[..n * 2]
+            }
+            None => &[],
+        };
+        let with_value = self.hash.as_ref().is_none_or(|h| !h.hashes_only);
+
+        match self.format {
+            Format::Text => {
+                out.extend_from_slice(hex);
+                if with_value {
+                    if !hex.is_empty() {
+                        out.push(b':');
+                    }
+                    out.extend_from_slice(value);
+                }
+            }
+            Format::Jsonl => {
+                out.extend_from_slice(b"{\"i\":");
+                push_decimal(index, 0, out);
+                if !hex.is_empty() {
+                    out.extend_from_slice(b",\"h\":\"");
+                    out.extend_from_slice(hex);
+                    out.push(b'"');
+                }
+                if with_value {
+                    out.extend_from_slice(b",\"v\":\"");
+                    push_json_escaped(value, out);
+                    out.push(b'"');
+                }
+                if self.with_length {
+                    out.extend_from_slice(b",\"len\":");
+                    push_decimal(candidate.len() as u64, 0, out);
+                }
+                if self.with_mask {
+                    out.extend_from_slice(b",\"m\":");
+                    push_decimal(mask as u64, 0, out);
+                }
+                out.push(b'}');
+            }
+            Format::Csv => {
+                push_decimal(index, 0, out);
+                if !hex.is_empty() {
+                    out.push(b',');
+                    out.extend_from_slice(hex);
+                }
+                if with_value {
+                    out.push(b',');
+                    if value.iter().any(|&b| matches!(b, b',' | b'"' | b'\r' | b'\n')) {
+                        out.push(b'"');
+                        for &b in value {
+                            if b == b'"' {
+                                out.push(b'"');
+                            }
+                            out.push(b);
+                        }
+                        out.push(b'"');
+                    } else {
+                        out.extend_from_slice(value);
+                    }
+                }
+                if self.with_length {
+                    out.push(b',');
+                    push_decimal(candidate.len() as u64, 0, out);
+                }
+                if self.with_mask {
+                    out.push(b',');
+                    push_decimal(mask as u64, 0, out);
+                }
+            }
+            Format::Tsv => {
+                push_decimal(index, 0, out);
+                if !hex.is_empty() {
+                    out.push(b'\t');
+                    out.extend_from_slice(hex);
+                }
+                if with_value {
+                    out.push(b'\t');
+                    for &b in value {
+                        match b {
+                            b'\t' => out.extend_from_slice(b"\\t"),
+                            b'\n' => out.extend_from_slice(b"\\n"),
+                            b'\r' => out.extend_from_slice(b"\\r"),
+                            b'\\' => out.extend_from_slice(b"\\\\"),
+                            _ => out.push(b),
+                        }
+                    }
+                }
+                if self.with_length {
+                    out.push(b'\t');
+                    push_decimal(candidate.len() as u64, 0, out);
+                }
+                if self.with_mask {
+                    out.push(b'\t');
+                    push_decimal(mask as u64, 0, out);
+                }
+            }
+        }
+        out.extend_from_slice(&self.delimiter);
+    }
+}
+
+// JSON strings must be UTF-8: valid text is escaped as usual, bytes that are
+// not part of a valid sequence are written as \u00XX.
+fn push_json_escaped(value: &[u8], out: &mut Vec<u8>) {
+    for chunk in value.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '"' => out.extend_from_slice(b"\\\""),
+                '\\' => out.extend_from_slice(b"\\\\"),
+                '\n' => out.extend_from_slice(b"\\n"),
+                '\r' => out.extend_from_slice(b"\\r"),
+                '\t' => out.extend_from_slice(b"\\t"),
+                c if (c as u32) < 0x20 => out.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes()),
+                c => {
+                    let mut tmp = [0u8; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut tmp).as_bytes());
+                }
+            }
+        }
+        for &b in chunk.invalid() {
+            out.extend_from_slice(format!("\\u{:04x}", b).as_bytes());
+        }
+    }
+}
@@ -0,0 +1,294 @@
+// rules.rs - Word mangling rules, a subset of the hashcat/john rule syntax
+//
+// One rule per line, functions applied left to right; `#` starts a comment.
+//   :  nothing          l  lowercase        u  uppercase         c  capitalize
+//   C  invert capitalize t toggle case     TN toggle case at N   r  reverse
+//   d  duplicate        pN append N copies  f  reflect           {  rotate left
+//   }  rotate right     $X append X         ^X prepend X         [  delete first
+//   ]  delete last      DN delete at N      'N truncate to N     xNM extract M from N
+//   iNX insert X at N   oNX overwrite at N  sXY replace X with Y @X purge X
+//   zN duplicate first char N times         ZN duplicate last char N times
+// Positions are 0-9 then A-Z (10-35). Functions whose position is out of range do nothing.
+
+use std::fs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Noop,
+    Lower,
+    Upper,
+    Capitalize,
+    InvertCapitalize,
+    ToggleAll,
+    Toggle(usize),
+    Reverse,
+    Duplicate,
+    DuplicateN(usize),
+    Reflect,
+    RotateLeft,
+    RotateRight,
+    Append(u8),
+    Prepend(u8),
+    DeleteFirst,
+    DeleteLast,
+    DeleteAt(usize),
+    Truncate(usize),
+    Extract(usize, usize),
+    Insert(usize, u8),
+    Overwrite(usize, u8),
+    Replace(u8, u8),
+    Purge(u8),
+    DupFirst(usize),
+    DupLast(usize),
+}
+
+#[derive(Clone, Debug)]
+pub struct Rule {
+    ops: Vec<Op>,
+}
+
+impl Rule {
+    pub fn parse(line: &str) -> Result<Rule, String> {
+        let bytes = line.as_bytes();
+        let mut ops = Vec::new();
+        let mut i = 0;
+        let arg = |i: usize| bytes.get(i).copied().ok_or_else(|| format!("missing argument for '{}'", bytes[i - 1] as char));
+        let pos = |i: usize| -> Result<usize, String> {
+            match arg(i)? {
+                c @ b'0'..=b'9' => Ok((c - b'0') as usize),
+                c @ b'A'..=b'Z' => Ok((c - b'A') as usize + 10),
+                c => Err(format!("invalid position '{}'", c as char)),
+            }
+        };
+        while i < bytes.len() {
+            let (op, used) = match bytes[i] {
+                b' ' | b'\t' => { i += 1; continue; }
+                b':' => (Op::Noop, 1),
+                b'l' => (Op::Lower, 1),
+                b'u' => (Op::Upper, 1),
+                b'c' => (Op::Capitalize, 1),
+                b'C' => (Op::InvertCapitalize, 1),
+                b't' => (Op::ToggleAll, 1),
+                b'T' => (Op::Toggle(pos(i + 1)?), 2),
+                b'r' => (Op::Reverse, 1),
+                b'd' => (Op::Duplicate, 1),
+                b'p' => (Op::DuplicateN(pos(i + 1)?), 2),
+                b'f' => (Op::Reflect, 1),
+                b'{' => (Op::RotateLeft, 1),
+                b'}' => (Op::RotateRight, 1),
+                b'$' => (Op::Append(arg(i + 1)?), 2),
+                b'^' => (Op::Prepend(arg(i + 1)?), 2),
+                b'[' => (Op::DeleteFirst, 1),
+                b']' => (Op::DeleteLast, 1),
+                b'D' => (Op::DeleteAt(pos(i + 1)?), 2),
+                b'\'' => (Op::Truncate(pos(i + 1)?), 2),
+                b'x' => (Op::Extract(pos(i + 1)?, pos(i + 2)?), 3),
+                b'i' => (Op::Insert(pos(i + 1)?, arg(i + 2)?), 3),
+                b'o' => (Op::Overwrite(pos(i + 1)?, arg(i + 2)?), 3),
+                b's' => (Op::Replace(arg(i + 1)?, arg(i + 2)?), 3),
+                b'@' => (Op::Purge(arg(i + 1)?), 2),
+                b'z' => (Op::DupFirst(pos(i + 1)?), 2),
+                b'Z' => (Op::DupLast(pos(i + 1)?), 2),
+                c => return Err(format!("unsupported rule function '{}'", c as char)),
+            };
+            ops.push(op);
+            i += used;
+        }
+        Ok(Rule { ops })
+    }
+
+    /// Rewrites the word in `out[from..]` in place.
+    #[inline]
+    pub fn apply(&self, out: &mut Vec<u8>, from: usize) {
+        for &op in &self.ops {
+            let len = out.len() - from;
+            match op {
+                Op::Noop => {}
+                Op::Lower => out[from..].make_ascii_lowercase(),
+                Op::Upper => out[from..].make_ascii_uppercase(),
+                Op::Capitalize | Op::InvertCapitalize => {
+                    let upper_first = op == Op::Capitalize;
+                    for (i, b) in out[from..].iter_mut().enumerate() {
+                        *b = if (i == 0) == upper_first { b.to_ascii_uppercase() } else { b.to_ascii_lowercase() };
+                    }
+                }
+                Op::ToggleAll => out[from..].iter_mut().for_each(toggle),
+                Op::Toggle(n) => if n < len { toggle(&mut out[from + n]) },
+                Op::Reverse => out[from..].reverse(),
+                Op::Duplicate => out.extend_from_within(from..),
+                Op::DuplicateN(n) => (0..n).for_each(|_| out.extend_from_within(from..from + len)),
+                Op::Reflect => {
+                    out.extend_from_within(from..);
+                    out[from + len..].reverse();
+                }
+                Op::RotateLeft => if len > 0 { out[from..].rotate_left(1) },
+                Op::RotateRight => if len > 0 { out[from..].rotate_right(1) },
+                Op::Append(c) => out.push(c),
+                Op::Prepend(c) => out.insert(from, c),
+                Op::DeleteFirst => if len > 0 { out.remove(from); },
+                Op::DeleteLast => if len > 0 { out.pop(); },
+                Op::DeleteAt(n) => if n < len { out.remove(from + n); },
+                Op::Truncate(n) => out.truncate(from + n.min(len)),
+                Op::Extract(n, m) => if n < len {
+                    let end = from + (n + m).min(len);
+                    out.truncate(end);
+                    out.drain(from..from + n);
+                },
+                Op::Insert(n, c) => if n <= len { out.insert(from + n, c) },
+                Op::Overwrite(n, c) => if n < len { out[from + n] = c },
+                Op::Replace(x, y) => out[from..].iter_mut().filter(|b| **b == x).for_each(|b| *b = y),
+                Op::Purge(x) => {
+                    let mut w = from;
+                    for r in from..out.len() {
+                        if out[r] != x {
+                            out[w] = out[r];
+                            w += 1;
+                        }
+                    }
+                    out.truncate(w);
+                }
+                Op::DupFirst(n) => if len > 0 {
+                    let c = out[from];
+                    out.splice(from..from, std::iter::repeat_n(c, n));
+                },
+                Op::DupLast(n) => if len > 0 {
+                    let c = out[out.len() - 1];
+                    out.extend(std::iter::repeat_n(c, n));
+                },
+            }
+        }
+    }
+
+    /// Longest output for an input of at most `len` bytes.
+    pub fn max_len(&self, len: usize) -> usize {
+        self.ops.iter().fold(len, |len, &op| match op {
+            Op::Duplicate | Op::Reflect => len * 2,
+            Op::DuplicateN(n) => len * (n + 1),
+            Op::Append(_) | Op::Prepend(_) | Op::Insert(..) => len + 1,
+            Op::DupFirst(n) | Op::DupLast(n) => len + n,
+            _ => len,
+        })
+    }
+}
+
+/// The rules of a rules file, each applied to every candidate.
+#[derive(Clone, Debug)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    pub fn parse(text: &str) -> Result<RuleSet, String> {
+        let mut rules = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim_end_matches('\r');
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            rules.push(Rule::parse(line).map_err(|e| format!("line {}: {}", n + 1, e))?);
+        }
+        Ok(RuleSet { rules })
+    }
+
+    pub fn load(path: &str) -> Result<RuleSet, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        RuleSet::parse(&text).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    #[inline]
+    pub fn apply(&self, rule: usize, out: &mut Vec<u8>, from: usize) {
+        self.rules[rule].apply(out, from);
+    }
+
+    pub fn max_len(&self, len: usize) -> usize {
+        self.rules.iter().map(|r| r.max_len(len)).max().unwrap_or(len)
+    }
+}
+
+#[inline]
+fn toggle(b: &mut u8) {
+    if b.is_ascii_alphabetic() {
+        *b ^= 0x20;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies `rule` after a record prefix, which must come through untouched.
+    fn mangle(rule: &str, word: &str) -> String {
+        let rule = Rule::parse(rule).unwrap();
+        let mut out = b"id,".to_vec();
+        out.extend_from_slice(word.as_bytes());
+        rule.apply(&mut out, 3);
+        assert_eq!(&out[..3], b"id,");
+        assert!(out.len() - 3 <= rule.max_len(word.len()));
+        String::from_utf8(out[3..].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn case_and_order_functions() {
+        assert_eq!(mangle(":", "pAss"), "pAss");
+        assert_eq!(mangle("l", "pAsS"), "pass");
+        assert_eq!(mangle("u", "pAs1"), "PAS1");
+        assert_eq!(mangle("c", "pASS"), "Pass");
+        assert_eq!(mangle("C", "pass"), "pASS");
+        assert_eq!(mangle("t", "pAs1"), "PaS1");
+        assert_eq!(mangle("T1 T9", "pass"), "pAss");
+        assert_eq!(mangle("r", "abc"), "cba");
+        assert_eq!(mangle("{", "abc"), "bca");
+        assert_eq!(mangle("}", "abc"), "cab");
+    }
+
+    #[test]
+    fn length_changing_functions() {
+        assert_eq!(mangle("d", "ab"), "abab");
+        assert_eq!(mangle("p2", "ab"), "ababab");
+        assert_eq!(mangle("f", "abc"), "abccba");
+        assert_eq!(mangle("$1 $!", "pw"), "pw1!");
+        assert_eq!(mangle("^1 ^2", "pw"), "21pw");
+        assert_eq!(mangle("[", "abc"), "bc");
+        assert_eq!(mangle("]", "abc"), "ab");
+        assert_eq!(mangle("D1", "abc"), "ac");
+        assert_eq!(mangle("'2", "abc"), "ab");
+        assert_eq!(mangle("x12", "abcd"), "bc");
+        assert_eq!(mangle("x29", "abcd"), "cd");
+        assert_eq!(mangle("i1- i5!", "abcd"), "a-bcd!");
+        assert_eq!(mangle("o0X", "abc"), "Xbc");
+        assert_eq!(mangle("sa@ ss$", "assa"), "@$$@");
+        assert_eq!(mangle("@s", "assa"), "aa");
+        assert_eq!(mangle("z2", "ab"), "aaab");
+        assert_eq!(mangle("Z2", "ab"), "abbb");
+        assert_eq!(mangle("d $1 c", "ab"), "Abab1");
+    }
+
+    #[test]
+    fn out_of_range_and_empty_words_are_left_alone() {
+        for rule in ["TA", "DA", "x9A", "i9X", "o9X", "{", "}", "[", "]", "z3", "Z3"] {
+            assert_eq!(mangle(rule, ""), "", "rule {}", rule);
+        }
+        assert_eq!(mangle("DA", "abc"), "abc");
+        assert_eq!(mangle("i3!", "abc"), "abc!");
+    }
+
+    #[test]
+    fn parse_errors_and_rule_files() {
+        for bad in ["$", "sa", "T", "Tz", "x1", "q", "l K"] {
+            assert!(Rule::parse(bad).is_err(), "{:?} should not parse", bad);
+        }
+        let set = RuleSet::parse("# comment\r\n:\r\n\r\n$1 $2\r\nd\n").unwrap();
+        assert_eq!(set.len(), 3);
+        assert_eq!(set.max_len(4), 8);
+        let err = RuleSet::parse(":\nl\n$").unwrap_err();
+        assert!(err.starts_with("line 3:"), "{}", err);
+    }
+}
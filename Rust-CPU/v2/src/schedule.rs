@@ -0,0 +1,145 @@
+// schedule.rs - Dynamic work distribution
+//
+// Workers repeatedly take the next fixed-size chunk of the index range from a
+// shared counter, so a slow worker only holds up the chunk it is on instead
+// of a whole 1/threads share. Chunks are numbered in index order and their
+// completion is tracked, which gives the index below which everything is done
+// (the --resume checkpoint) and the order in which outputs belong.
+
+use std::collections::BTreeSet;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Chunks handed out per worker when the size is derived from the range.
+pub const CHUNKS_PER_THREAD: u64 = 64;
+/// Upper bound on derived chunk sizes, small enough to keep workers balanced.
+pub const MAX_CHUNK: u64 = 1 << 16;
+
+/// One unit of work: the `seq`-th chunk of the range.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    pub seq: u64,
+    pub range: Range<u64>,
+}
+
+struct Completed {
+    /// Chunks `0..contiguous` are all done.
+    contiguous: u64,
+    /// Done chunks above `contiguous`.
+    pending: BTreeSet<u64>,
+}
+
+/// Hands out `range` in chunks of `chunk` indices, the last one possibly shorter.
+pub struct Scheduler {
+    start: u64,
+    end: u64,
+    chunk: u64,
+    chunks: u64,
+    next: AtomicU64,
+    completed: Mutex<Completed>,
+}
+
+impl Scheduler {
+    pub fn new(range: Range<u64>, chunk: u64) -> Scheduler {
+        let len = range.end.saturating_sub(range.start);
+        // A chunk never exceeds the range, which keeps chunk offsets within u64
+        let chunk = chunk.min(len).max(1);
+        let chunks = len.div_ceil(chunk);
+        Scheduler {
+            start: range.start,
+            end: range.end,
+            chunk,
+            chunks,
+            next: AtomicU64::new(0),
+            completed: Mutex::new(Completed { contiguous: 0, pending: BTreeSet::new() }),
+        }
+    }
+
+    /// Chunk size giving every worker about `CHUNKS_PER_THREAD` chunks, capped at `MAX_CHUNK`.
+    pub fn auto_chunk(len: u64, threads: usize) -> u64 {
+        (len / (threads.max(1) as u64 * CHUNKS_PER_THREAD)).clamp(1, MAX_CHUNK)
+    }
+
+    pub fn chunk_size(&self) -> u64 {
+        self.chunk
+    }
+
+    /// Number of chunks in the range.
+    pub fn chunks(&self) -> u64 {
+        self.chunks
+    }
+
+    /// Takes the next chunk, `None` once the range is exhausted.
+    pub fn next(&self) -> Option<Chunk> {
+        let seq = self.next.fetch_add(1, Ordering::Relaxed);
+        if seq >= self.chunks {
+            return None;
+        }
+        let first = self.start.saturating_add(seq.saturating_mul(self.chunk));
+        Some(Chunk { seq, range: first..first.saturating_add(self.chunk).min(self.end) })
+    }
+
+    /// Marks a chunk as finished. Chunks given up half way must not be reported.
+    pub fn complete(&self, seq: u64) {
+        let mut done = self.completed.lock().unwrap();
+        if seq != done.contiguous {
+            done.pending.insert(seq);
+            return;
+        }
+        done.contiguous += 1;
+        loop {
+            let next = done.contiguous;
+            if !done.pending.remove(&next) {
+                break;
+            }
+            done.contiguous += 1;
+        }
+    }
+
+    /// Every index below the checkpoint belongs to a finished chunk.
+    pub fn checkpoint(&self) -> u64 {
+        let done = self.completed.lock().unwrap().contiguous;
+        self.start.saturating_add(done.saturating_mul(self.chunk)).min(self.end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunks_cover_the_range() {
+        let s = Scheduler::new(10..35, 10);
+        assert_eq!(s.chunks(), 3);
+        let ranges: Vec<Range<u64>> = std::iter::from_fn(|| s.next()).map(|c| c.range).collect();
+        assert_eq!(ranges, vec![10..20, 20..30, 30..35]);
+        assert_eq!(s.next(), None);
+    }
+
+    #[test]
+    fn checkpoint_waits_for_earlier_chunks() {
+        let s = Scheduler::new(100..150, 10);
+        let chunks: Vec<Chunk> = std::iter::from_fn(|| s.next()).collect();
+        assert_eq!(s.checkpoint(), 100);
+        s.complete(2);
+        s.complete(1);
+        assert_eq!(s.checkpoint(), 100);
+        s.complete(0);
+        assert_eq!(s.checkpoint(), 130);
+        s.complete(4);
+        assert_eq!(s.checkpoint(), 130);
+        s.complete(chunks[3].seq);
+        assert_eq!(s.checkpoint(), 150);
+    }
+
+    #[test]
+    fn huge_chunks_do_not_overflow() {
+        let s = Scheduler::new(1..4, u64::MAX);
+        assert_eq!(s.chunk_size(), 3);
+        assert_eq!(s.next().map(|c| c.range), Some(1..4));
+        assert_eq!(s.next(), None);
+        s.complete(0);
+        assert_eq!(s.checkpoint(), 4);
+    }
+}
@@ -0,0 +1,148 @@
+// search.rs - Finding one candidate instead of writing the whole keyspace
+//
+// Workers test candidates in place against a predicate (for example a target
+// hash) and stop together on the first match. Workers take chunks of the range
+// and publish their position so an interrupted search can resume from a safe index.
+
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::encoding;
+use crate::hash::{HashAlgo, MAX_DIGEST};
+use crate::keyspace::Keyspace;
+use crate::schedule::Scheduler;
+
+/// Decides whether a candidate is the one being searched for.
+pub type Predicate = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// Candidates between checks of the stop flag and position updates.
+pub const CHECK_INTERVAL: u64 = 4096;
+
+/// A digest to find, e.g. `--find-hash <hex> --hash-algo sha256`.
+pub struct HashTarget {
+    algo: Arc<dyn HashAlgo>,
+    digest: Vec<u8>,
+}
+
+impl HashTarget {
+    pub fn new(algo: Arc<dyn HashAlgo>, hex: &str) -> Result<HashTarget, String> {
+        let digest = encoding::decode_hex(hex)?;
+        if digest.len() != algo.output_len() {
+            return Err(format!("{} hashes have {} hex digits, got {}", algo.name(), algo.output_len() * 2, digest.len() * 2));
+        }
+        Ok(HashTarget { algo, digest })
+    }
+
+    pub fn algo(&self) -> &dyn HashAlgo {
+        self.algo.as_ref()
+    }
+
+    #[inline]
+    pub fn matches(&self, candidate: &[u8]) -> bool {
+        let mut out = [0u8; MAX_DIGEST];
+        self.algo.digest(candidate, &mut out);
+        out[..self.digest.len()] == self.digest[..]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Found {
+    pub index: u64,
+    pub candidate: Vec<u8>,
+}
+
+/// State shared by the workers of one search.
+pub struct SearchState {
+    found: Mutex<Option<Found>>,
+    done: AtomicBool,
+    /// Next untested index per worker, `u64::MAX` once the worker has finished.
+    positions: Vec<AtomicU64>,
+    end: u64,
+}
+
+impl SearchState {
+    /// `starts` holds the first index of every worker, `end` the end of the searched range.
+    pub fn new(starts: &[u64], end: u64) -> SearchState {
+        SearchState {
+            found: Mutex::new(None),
+            done: AtomicBool::new(false),
+            positions: starts.iter().map(|&s| AtomicU64::new(s)).collect(),
+            end,
+        }
+    }
+
+    /// Records a match and tells all workers to stop. When several workers
+    /// match at once, the lowest index wins.
+    pub fn report(&self, index: u64, candidate: &[u8]) {
+        let mut found = self.found.lock().unwrap();
+        if found.as_ref().is_none_or(|f| index < f.index) {
+            *found = Some(Found { index, candidate: candidate.to_vec() });
+        }
+        self.done.store(true, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    pub fn found(&self) -> Option<Found> {
+        self.found.lock().unwrap().clone()
+    }
+
+    #[inline]
+    pub fn set_position(&self, worker: usize, index: u64) {
+        self.positions[worker].store(index, Ordering::Relaxed);
+    }
+
+    pub fn finish(&self, worker: usize) {
+        self.positions[worker].store(u64::MAX, Ordering::Relaxed);
+    }
+
+    /// Every index below the checkpoint has been tested.
+    pub fn checkpoint(&self) -> u64 {
+        self.positions.iter().map(|p| p.load(Ordering::Relaxed)).min().unwrap_or(u64::MAX).min(self.end)
+    }
+}
+
+/// Tests the candidates of `keyspace` in `range` on `threads` workers and
+/// returns the match with the lowest index found before all workers stopped.
+pub fn search(keyspace: &Keyspace, range: Range<u64>, threads: usize, predicate: &Predicate) -> Option<Found> {
+    let len = range.end - range.start;
+    let scheduler = Scheduler::new(range.clone(), Scheduler::auto_chunk(len, threads));
+    let threads = threads.max(1).min(scheduler.chunks().max(1) as usize);
+    let state = SearchState::new(&vec![range.start; threads], range.end);
+
+    thread::scope(|s| {
+        for w in 0..threads {
+            let (state, scheduler) = (&state, &scheduler);
+            s.spawn(move || {
+                let mut digits = vec![0u64; keyspace.len()];
+                let mut candidate = Vec::new();
+                while let Some(chunk) = scheduler.next() {
+                    let start = chunk.range.start;
+                    keyspace.index_to_digits(start, &mut digits);
+                    for index in chunk.range {
+                        if (index - start) % CHECK_INTERVAL == 0 {
+                            if state.is_done() {
+                                return;
+                            }
+                            state.set_position(w, index);
+                        }
+                        candidate.clear();
+                        keyspace.render(&digits, b"", &mut candidate);
+                        if predicate(&candidate) {
+                            state.report(index, &candidate);
+                            return;
+                        }
+                        keyspace.increment(&mut digits);
+                    }
+                }
+                state.finish(w);
+            });
+        }
+    });
+    state.found()
+}
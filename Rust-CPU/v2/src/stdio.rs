@@ -0,0 +1,43 @@
+// stdio.rs - Status output routing and pipe handling
+// When combinations go to stdout, banners, progress and reports move to stderr
+// so they never mix with the data stream.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn set_status_to_stderr(enabled: bool) {
+    STATUS_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+pub fn status_to_stderr() -> bool {
+    STATUS_TO_STDERR.load(Ordering::Relaxed)
+}
+
+/// `println!` for status messages, redirected to stderr while data goes to stdout.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::stdio::status_to_stderr() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub fn is_broken_pipe(e: &io::Error) -> bool {
+    e.kind() == io::ErrorKind::BrokenPipe
+}
+
+/// Unwraps a write result. A closed pipe (`combo_gen 8 | head`) ends the
+/// process quietly, any other error aborts with `what` as context.
+pub fn check_write(result: io::Result<()>, what: &str) {
+    if let Err(e) = result {
+        if is_broken_pipe(&e) {
+            std::process::exit(0);
+        }
+        panic!("{}: {}", what, e);
+    }
+}
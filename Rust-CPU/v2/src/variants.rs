@@ -0,0 +1,83 @@
+// variants.rs - Case and substitution variants of input words
+//
+// Every byte of a word becomes one slot holding the byte itself, its other
+// case and its substitutes, so the variants of a word form a mixed-radix
+// keyspace and several words form a `KeyspaceList`.
+
+use crate::keyspace::{Keyspace, Slot};
+
+/// Common leetspeak substitutions used by `--leet`.
+pub const LEET: &str = "a=@4,b=8,e=3,g=9,i=1!,l=1,o=0,s=$5,t=7,z=2";
+
+/// Replacement bytes per input byte, looked up case-insensitively for letters.
+#[derive(Clone, Debug)]
+pub struct Substitutions {
+    table: Vec<Vec<u8>>,
+}
+
+impl Default for Substitutions {
+    fn default() -> Self {
+        Substitutions { table: vec![Vec::new(); 256] }
+    }
+}
+
+impl Substitutions {
+    /// Parses `a=@4,e=3,o=0` (`a→@4` is accepted too). Specs can be given
+    /// more than once, their replacements add up.
+    pub fn add(&mut self, spec: &str) -> Result<(), String> {
+        for entry in spec.split(',').filter(|e| !e.is_empty()) {
+            let (from, to) = entry.split_once('=').or_else(|| entry.split_once('→'))
+                .ok_or_else(|| format!("substitution '{}' must look like a=@4", entry))?;
+            let &[from] = from.as_bytes() else {
+                return Err(format!("substitution '{}' must replace a single byte", entry));
+            };
+            let list = &mut self.table[from.to_ascii_lowercase() as usize];
+            for &b in to.as_bytes() {
+                if !list.contains(&b) {
+                    list.push(b);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.table.iter().all(Vec::is_empty)
+    }
+
+    fn of(&self, b: u8) -> &[u8] {
+        &self.table[b.to_ascii_lowercase() as usize]
+    }
+}
+
+/// The variants of `word`: the word as given comes first, then the odometer
+/// runs through other cases (`case`) and substitutes of every byte.
+pub fn expand(word: &[u8], case: bool, subs: &Substitutions) -> Keyspace {
+    let mut slots = Vec::new();
+    let mut fixed = Vec::new();
+    for &b in word {
+        let mut tokens = vec![b];
+        if case && b.is_ascii_alphabetic() {
+            tokens.push(b ^ 0x20);
+        }
+        for &s in subs.of(b) {
+            if !tokens.contains(&s) {
+                tokens.push(s);
+            }
+        }
+        // Runs of bytes without variants become one literal slot
+        if tokens.len() == 1 {
+            fixed.push(b);
+            continue;
+        }
+        if !fixed.is_empty() {
+            slots.push(Slot::literal(&fixed));
+            fixed.clear();
+        }
+        slots.push(Slot::from_tokens(tokens.iter().map(std::slice::from_ref)));
+    }
+    if !fixed.is_empty() {
+        slots.push(Slot::literal(&fixed));
+    }
+    Keyspace::new(slots)
+}
@@ -0,0 +1,22 @@
+// wordlist.rs - Reading newline separated word lists
+
+use std::fs;
+use std::io::{self, Read};
+
+/// Reads a word list from `path`, or from stdin when `path` is `-`.
+pub fn read(path: &str) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut data = Vec::new();
+        io::stdin().lock().read_to_end(&mut data)?;
+        Ok(data)
+    } else {
+        fs::read(path)
+    }
+}
+
+/// The words of a list: one per line, `\r\n` or `\n` terminated. Empty lines are skipped.
+pub fn lines(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    data.split(|&b| b == b'\n')
+        .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+        .filter(|l| !l.is_empty())
+}
@@ -0,0 +1,252 @@
+// writer.rs - Dedicated output thread fed by the workers
+//
+// Workers fill buffers and send them through a bounded channel to one writer
+// thread per output, which writes them and hands every buffer back to the
+// worker that sent it. Each worker cycles through a fixed set of buffers, so
+// memory stays bounded and generation only waits for the disk when all of
+// them are in flight.
+//
+// Blocks carry the scheduler chunk they belong to. With ordering enabled the
+// writer holds back blocks of later chunks until every earlier chunk has been
+// written, which makes the output of any number of workers identical to a
+// single-threaded run. Every chunk a worker started must then be closed with
+// a `last` block, even an empty one, or later chunks are never written.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Buffers per worker: one being filled, the rest queued or being written.
+pub const BUFFERS_PER_WORKER: usize = 4;
+
+struct Block {
+    seq: u64,
+    last: bool,
+    data: Vec<u8>,
+    back: Sender<Vec<u8>>,
+}
+
+/// Owns the writer thread of one output.
+pub struct Writer {
+    tx: SyncSender<Block>,
+    failed: Arc<AtomicBool>,
+    handle: JoinHandle<io::Result<u64>>,
+}
+
+impl Writer {
+    /// Starts a writer thread for `sink`. `capacity` bounds the blocks waiting
+    /// in the channel; `ordered` writes chunks in sequence order starting at 0.
+    pub fn spawn(mut sink: Box<dyn Write + Send>, capacity: usize, ordered: bool) -> Writer {
+        let (tx, rx) = mpsc::sync_channel::<Block>(capacity.max(1));
+        let failed = Arc::new(AtomicBool::new(false));
+        let failed_writer = Arc::clone(&failed);
+        let handle = thread::spawn(move || {
+            let mut state = WriterState { sink: &mut *sink, written: 0, error: None, failed: &failed_writer };
+            let mut next = 0u64;
+            let mut held: BTreeMap<u64, Vec<Block>> = BTreeMap::new();
+            for block in rx {
+                if !ordered {
+                    state.write(block);
+                    continue;
+                }
+                if block.seq != next {
+                    held.entry(block.seq).or_default().push(block);
+                    continue;
+                }
+                let mut done = block.last;
+                state.write(block);
+                // A finished chunk releases whatever later chunks have already arrived
+                while done {
+                    next += 1;
+                    done = false;
+                    for block in held.remove(&next).unwrap_or_default() {
+                        done = block.last;
+                        state.write(block);
+                    }
+                }
+            }
+            // Chunks that were never closed only exist when generation stopped early
+            for block in held.into_values().flatten() {
+                state.write(block);
+            }
+            if state.error.is_none() {
+                if let Err(e) = state.sink.flush() {
+                    state.error = Some(e);
+                }
+            }
+            match state.error {
+                Some(e) => Err(e),
+                None => Ok(state.written),
+            }
+        });
+        Writer { tx, failed, handle }
+    }
+
+    /// A handle for one worker with its own `buffers` buffers of `buffer_size` bytes.
+    pub fn sender(&self, buffers: usize, buffer_size: usize) -> BlockSender {
+        let (back_tx, back_rx) = mpsc::channel();
+        BlockSender { tx: self.tx.clone(), failed: Arc::clone(&self.failed), back_tx, back_rx, allocated: 0, buffers: buffers.max(1), buffer_size }
+    }
+
+    /// Waits until every block has been written and the output is flushed.
+    /// Returns the bytes written or the first write error.
+    pub fn finish(self) -> io::Result<u64> {
+        drop(self.tx);
+        self.handle.join().unwrap_or_else(|_| Err(io::Error::other("writer thread panicked")))
+    }
+}
+
+struct WriterState<'a> {
+    sink: &'a mut (dyn Write + Send),
+    written: u64,
+    error: Option<io::Error>,
+    failed: &'a AtomicBool,
+}
+
+impl WriterState<'_> {
+    // After a failure blocks are only recycled, so no worker waits for a buffer forever
+    fn write(&mut self, mut block: Block) {
+        if self.error.is_none() {
+            match self.sink.write_all(&block.data) {
+                Ok(()) => self.written += block.data.len() as u64,
+                Err(e) => {
+                    self.failed.store(true, Ordering::Relaxed);
+                    self.error = Some(e);
+                }
+            }
+        }
+        block.data.clear();
+        let _ = block.back.send(block.data);
+    }
+}
+
+/// A worker's end of the pipeline: sends filled buffers and recycles the
+/// ones the writer is done with.
+pub struct BlockSender {
+    tx: SyncSender<Block>,
+    failed: Arc<AtomicBool>,
+    back_tx: Sender<Vec<u8>>,
+    back_rx: Receiver<Vec<u8>>,
+    allocated: usize,
+    buffers: usize,
+    buffer_size: usize,
+}
+
+impl BlockSender {
+    /// An empty buffer; blocks while all of this worker's buffers are in flight.
+    pub fn buffer(&mut self) -> Vec<u8> {
+        if let Ok(buf) = self.back_rx.try_recv() {
+            return buf;
+        }
+        if self.allocated < self.buffers {
+            self.allocated += 1;
+            return Vec::with_capacity(self.buffer_size);
+        }
+        // The writer holds the other end of every block, so this returns once one is written
+        self.back_rx.recv().unwrap_or_else(|_| Vec::with_capacity(self.buffer_size))
+    }
+
+    /// Queues `data` as a block of chunk `seq`; `last` closes the chunk.
+    /// Fails once the writer has hit a write error; the buffer stays in the pool.
+    pub fn send(&mut self, seq: u64, last: bool, mut data: Vec<u8>) -> io::Result<()> {
+        if !self.failed.load(Ordering::Relaxed) {
+            match self.tx.send(Block { seq, last, data, back: self.back_tx.clone() }) {
+                Ok(()) => return Ok(()),
+                Err(mpsc::SendError(block)) => data = block.data,
+            }
+        }
+        data.clear();
+        let _ = self.back_tx.send(data);
+        Err(io::Error::new(io::ErrorKind::BrokenPipe, "output closed"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct Full;
+
+    impl Write for Full {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn block(sender: &mut BlockSender, text: &str) -> Vec<u8> {
+        let mut buf = sender.buffer();
+        buf.extend_from_slice(text.as_bytes());
+        buf
+    }
+
+    #[test]
+    fn ordered_blocks_are_written_in_chunk_order() {
+        let sink = Shared::default();
+        let writer = Writer::spawn(Box::new(sink.clone()), 16, true);
+        let mut a = writer.sender(BUFFERS_PER_WORKER, 16);
+        let mut b = writer.sender(BUFFERS_PER_WORKER, 16);
+        // Chunk 2 and the first half of chunk 1 arrive before chunk 0
+        let data = block(&mut b, "2a ");
+        b.send(2, true, data).unwrap();
+        let data = block(&mut a, "1a ");
+        a.send(1, false, data).unwrap();
+        let data = block(&mut b, "0a ");
+        b.send(0, false, data).unwrap();
+        let data = block(&mut b, "0b ");
+        b.send(0, true, data).unwrap();
+        let data = block(&mut a, "1b ");
+        a.send(1, true, data).unwrap();
+        drop((a, b));
+        assert_eq!(writer.finish().unwrap(), 15);
+        assert_eq!(sink.0.lock().unwrap().as_slice(), b"0a 0b 1a 1b 2a ");
+    }
+
+    #[test]
+    fn unordered_blocks_are_written_as_they_arrive() {
+        let sink = Shared::default();
+        let writer = Writer::spawn(Box::new(sink.clone()), 16, false);
+        let mut a = writer.sender(1, 16);
+        for (seq, text) in [(3, "x"), (0, "y"), (1, "z")] {
+            let data = block(&mut a, text);
+            a.send(seq, true, data).unwrap();
+        }
+        drop(a);
+        writer.finish().unwrap();
+        assert_eq!(sink.0.lock().unwrap().as_slice(), b"xyz");
+    }
+
+    #[test]
+    fn write_errors_reach_senders_and_finish() {
+        let writer = Writer::spawn(Box::new(Full), 1, false);
+        let mut a = writer.sender(1, 16);
+        let data = block(&mut a, "lost");
+        a.send(0, true, data).unwrap();
+        // The buffer comes back once the failed block has been handled
+        let data = block(&mut a, "lost");
+        assert!(a.send(1, true, data).is_err());
+        drop(a);
+        assert_eq!(writer.finish().unwrap_err().kind(), io::ErrorKind::StorageFull);
+    }
+}